@@ -0,0 +1,211 @@
+use crate::encoding::{base45_decode, base45_encode};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+
+// every chunk reads "QRB1:<id>:<index>/<count>:<crc32>:<base45 data>", which keeps the whole
+// code in the alphanumeric mode; chunk 0 is the manifest, the others carry the deflated file
+const CHUNK_V1: &str = "QRB1";
+// more codes than anyone would print, it also bounds what a forged chunk can make us allocate
+const MAX_CHUNKS: usize = 9999;
+
+struct Manifest {
+    size: usize,
+    sha256: String,
+    file_name: String,
+}
+
+// compresses the file and splits it into chunks of at most chunk_size bytes before base45
+#[allow(dead_code)]
+pub fn backup_chunks(
+    file_name: &str,
+    data: &[u8],
+    chunk_size: usize,
+) -> Result<Vec<String>, String> {
+    if chunk_size == 0 {
+        return Err("the chunk size has to be at least one byte".to_string());
+    }
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    let compressed = encoder
+        .write_all(data)
+        .and_then(|_| encoder.finish())
+        .map_err(|e| format!("could not compress the file: {}", e))?;
+
+    let sha256 = hex(&Sha256::digest(data));
+    // the id ties the chunks of one backup together so they can not be mixed up
+    let id = sha256[..8].to_uppercase();
+    let manifest = format!("deflate\n{}\n{}\n{}", data.len(), sha256, file_name);
+
+    let parts = std::iter::once(manifest.as_bytes())
+        .chain(compressed.chunks(chunk_size))
+        .collect::<Vec<_>>();
+    let count = parts.len() - 1;
+    if count > MAX_CHUNKS {
+        return Err(format!(
+            "the file would need {} codes, pick a larger chunk size",
+            count
+        ));
+    }
+    Ok(parts
+        .iter()
+        .enumerate()
+        .map(|(i, part)| {
+            format!(
+                "{}:{}:{}/{}:{:08X}:{}",
+                CHUNK_V1,
+                id,
+                i,
+                count,
+                crc32fast::hash(part),
+                base45_encode(part)
+            )
+        })
+        .collect())
+}
+
+// reassembles the chunks in any order, returning the original file name and content
+#[allow(dead_code)]
+pub fn restore_chunks(chunks: &[String]) -> Result<(String, Vec<u8>), String> {
+    let mut id: Option<String> = None;
+    let mut count = None;
+    let mut parts: Vec<Option<Vec<u8>>> = Vec::new();
+
+    for chunk in chunks.iter().map(|c| c.trim()).filter(|c| !c.is_empty()) {
+        let (chunk_id, index, chunk_count, data) = parse_chunk(chunk)?;
+        match &id {
+            Some(i) if *i != chunk_id => {
+                return Err(format!(
+                    "the chunks belong to different backups ({} and {})",
+                    i, chunk_id
+                ))
+            }
+            Some(_) => {}
+            None => {
+                id = Some(chunk_id);
+                count = Some(chunk_count);
+                parts = vec![None; chunk_count + 1];
+            }
+        }
+        if count != Some(chunk_count) {
+            return Err("the chunks disagree about the number of codes".to_string());
+        }
+        // duplicates are fine, scanning a code twice is an easy mistake to make
+        parts[index] = Some(data);
+    }
+
+    let count = count.ok_or_else(|| "no chunks were given".to_string())?;
+    let missing = (0..=count)
+        .filter(|i| parts[*i].is_none())
+        .map(|i| {
+            if i == 0 {
+                "0 (the manifest)".to_string()
+            } else {
+                i.to_string()
+            }
+        })
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        return Err(format!(
+            "{} of {} codes are missing: {}",
+            missing.len(),
+            count + 1,
+            missing.join(", ")
+        ));
+    }
+
+    let mut parts = parts.into_iter().map(Option::unwrap);
+    let manifest = parse_manifest(&parts.next().unwrap())?;
+    let compressed = parts.flatten().collect::<Vec<u8>>();
+
+    // a damaged or forged stream must not inflate past the size the manifest promises
+    let mut data = Vec::new();
+    DeflateDecoder::new(compressed.as_slice())
+        .take(manifest.size as u64 + 1)
+        .read_to_end(&mut data)
+        .map_err(|e| format!("could not decompress the backup: {}", e))?;
+    if data.len() != manifest.size || hex(&Sha256::digest(&data)) != manifest.sha256 {
+        return Err("the restored file does not match the hash in the manifest".to_string());
+    }
+    Ok((manifest.file_name, data))
+}
+
+// returns the backup id, the chunk index, the number of data chunks and the checked data
+fn parse_chunk(chunk: &str) -> Result<(String, usize, usize, Vec<u8>), String> {
+    let malformed = || format!("{:?} is not a qrterm backup chunk", shorten(chunk));
+    let fields = chunk.splitn(5, ':').collect::<Vec<_>>();
+    if fields.len() != 5 || fields[0] != CHUNK_V1 || fields[1].len() != 8 {
+        return Err(malformed());
+    }
+    let (index, count) = fields[2]
+        .split_once('/')
+        .and_then(|(i, n)| Some((i.parse::<usize>().ok()?, n.parse::<usize>().ok()?)))
+        .filter(|(i, n)| i <= n && *n <= MAX_CHUNKS)
+        .ok_or_else(malformed)?;
+    let crc = u32::from_str_radix(fields[3], 16).map_err(|_| malformed())?;
+    let data = base45_decode(fields[4]).ok_or_else(malformed)?;
+    if crc32fast::hash(&data) != crc {
+        return Err(format!("chunk {} of {} is damaged", index, count));
+    }
+    Ok((fields[1].to_string(), index, count, data))
+}
+
+fn parse_manifest(data: &[u8]) -> Result<Manifest, String> {
+    let err = || "the manifest is damaged".to_string();
+    let text = std::str::from_utf8(data).map_err(|_| err())?;
+    let fields = text.splitn(4, '\n').collect::<Vec<_>>();
+    match fields.as_slice() {
+        ["deflate", size, sha256, file_name] => Ok(Manifest {
+            size: size.parse().map_err(|_| err())?,
+            sha256: sha256.to_string(),
+            file_name: file_name.to_string(),
+        }),
+        [compression, ..] if *compression != "deflate" => Err(format!(
+            "the compression {:?} is not supported",
+            compression
+        )),
+        _ => Err(err()),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn shorten(text: &str) -> String {
+    text.chars().take(24).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backups_round_trip_in_any_order() {
+        let data = (0..5000u32)
+            .flat_map(|i| i.to_le_bytes())
+            .collect::<Vec<_>>();
+        let mut chunks = backup_chunks("numbers.bin", &data, 500).unwrap();
+        assert!(chunks.len() > 3);
+        chunks.reverse();
+        chunks.push(chunks[1].clone());
+        assert_eq!(
+            restore_chunks(&chunks).unwrap(),
+            ("numbers.bin".to_string(), data)
+        );
+
+        chunks.remove(0);
+        chunks.pop();
+        let missing = restore_chunks(&chunks).unwrap_err();
+        assert!(missing.starts_with("1 of "), "{}", missing);
+    }
+
+    #[test]
+    fn forged_chunk_counts_are_rejected() {
+        for count in ["18446744073709551615", "4000000000", "10000"].iter() {
+            let chunk = format!("QRB1:0123ABCD:0/{}:00000000:", count);
+            assert!(restore_chunks(&[chunk]).is_err());
+        }
+    }
+}
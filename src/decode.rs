@@ -0,0 +1,347 @@
+use image::GrayImage;
+use qrcode::bits::Bits;
+use qrcode::canvas::{is_functional, Canvas, MaskPattern};
+use qrcode::ec::construct_codewords;
+use qrcode::types::Color;
+use qrcode::{EcLevel, Version};
+
+// reads a single, upright QR-Code from an image file
+pub fn decode_image_file(path: &str) -> Result<Vec<u8>, String> {
+    let image = image::open(path)
+        .map_err(|e| format!("could not read {:?}: {}", path, e))?
+        .to_luma();
+    decode_image(&image).map_err(|e| format!("could not decode {:?}: {}", path, e))
+}
+
+// this only understands clean images like the ones qrterm writes, photos of codes
+// need a real scanner app
+pub fn decode_image(image: &GrayImage) -> Result<Vec<u8>, String> {
+    let dark = |x: u32, y: u32| image.get_pixel(x, y).data[0] < 128;
+
+    // the bounding box of all dark pixels is the code without its quiet zone
+    let (mut left, mut top, mut right, mut bottom) = (u32::MAX, u32::MAX, 0, 0);
+    for (x, y, _) in image.enumerate_pixels().filter(|(x, y, _)| dark(*x, *y)) {
+        left = left.min(x);
+        top = top.min(y);
+        right = right.max(x);
+        bottom = bottom.max(y);
+    }
+    if left > right {
+        return Err("the image is empty".to_string());
+    }
+
+    // the top left finder pattern is seven modules wide and high
+    let run_x = (left..=right).take_while(|&x| dark(x, top)).count() as f64;
+    let run_y = (top..=bottom).take_while(|&y| dark(left, y)).count() as f64;
+    let (module_x, module_y) = (run_x / 7.0, run_y / 7.0);
+    let width = ((right - left + 1) as f64 / module_x).round() as i16;
+    if !(21..=177).contains(&width) || (width - 17) % 4 != 0 {
+        return Err("no QR-Code was found".to_string());
+    }
+    let version = Version::Normal((width - 17) / 4);
+    let sample = |x: i16, y: i16| {
+        (
+            left + ((f64::from(x) + 0.5) * module_x) as u32,
+            top + ((f64::from(y) + 0.5) * module_y) as u32,
+        )
+    };
+    // a stray image can have a bounding box the sampling grid does not fit in
+    let (last_x, last_y) = sample(width - 1, width - 1);
+    if last_x >= image.width() || last_y >= image.height() {
+        return Err("no QR-Code was found".to_string());
+    }
+    let module = |x: i16, y: i16| {
+        let (x, y) = sample(x, y);
+        dark(x, y)
+    };
+
+    let (ec_level, mask) = find_format(version, width, &module)?;
+
+    // read the codewords in the zigzag order they were placed in
+    let mut raw = Vec::new();
+    let mut byte = 0u8;
+    let mut bits = 0;
+    for (x, y) in data_modules(version, width) {
+        byte = (byte << 1) | u8::from(module(x, y) != is_masked(mask, x, y));
+        bits += 1;
+        if bits == 8 {
+            raw.push(byte);
+            byte = 0;
+            bits = 0;
+        }
+    }
+
+    let data = deinterleave(&raw, version, ec_level)?;
+    parse_segments(&data, version)
+}
+
+// finds the error correction level and mask whose format information matches the image
+fn find_format<F: Fn(i16, i16) -> bool>(
+    version: Version,
+    width: i16,
+    module: &F,
+) -> Result<(EcLevel, MaskPattern), String> {
+    const MASKS: [MaskPattern; 8] = [
+        MaskPattern::Checkerboard,
+        MaskPattern::HorizontalLines,
+        MaskPattern::VerticalLines,
+        MaskPattern::DiagonalLines,
+        MaskPattern::LargeCheckerboard,
+        MaskPattern::Fields,
+        MaskPattern::Diamonds,
+        MaskPattern::Meadow,
+    ];
+    let mut best = None;
+    for &ec_level in &[EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H] {
+        for &mask in MASKS.iter() {
+            let mut canvas = Canvas::new(version, ec_level);
+            canvas.draw_all_functional_patterns();
+            canvas.apply_mask(mask);
+            let colors = canvas.into_colors();
+            let mismatches = (0..width)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .filter(|&(x, y)| is_function_module(version, width, x, y))
+                .filter(|&(x, y)| {
+                    let expected = colors[(y * width + x) as usize] == Color::Dark;
+                    module(x, y) != expected
+                })
+                .count();
+            if best.as_ref().is_none_or(|(m, _, _)| mismatches < *m) {
+                best = Some((mismatches, ec_level, mask));
+            }
+        }
+    }
+    match best {
+        // a few wrong modules are fine, anything more is not a QR-Code
+        Some((m, ec_level, mask)) if m < 16 => Ok((ec_level, mask)),
+        _ => Err("the format information is unreadable".to_string()),
+    }
+}
+
+// the data modules from the bottom right, two columns at a time, skipping the timing column
+fn data_modules(version: Version, width: i16) -> Vec<(i16, i16)> {
+    let mut modules = Vec::new();
+    let mut right = width - 1;
+    let mut upwards = true;
+    while right > 0 {
+        if right == 6 {
+            right -= 1;
+        }
+        for i in 0..width {
+            let y = if upwards { width - 1 - i } else { i };
+            for x in &[right, right - 1] {
+                if !is_function_module(version, width, *x, y) {
+                    modules.push((*x, y));
+                }
+            }
+        }
+        upwards = !upwards;
+        right -= 2;
+    }
+    modules
+}
+
+// the qrcode crate leaves out the version information blocks of version 7 and up
+fn is_function_module(version: Version, width: i16, x: i16, y: i16) -> bool {
+    let has_version_info = matches!(version, Version::Normal(v) if v >= 7);
+    let in_version_info = |a: i16, b: i16| a >= width - 11 && a < width - 8 && b < 6;
+    is_functional(version, width, x, y)
+        || has_version_info && (in_version_info(x, y) || in_version_info(y, x))
+}
+
+fn is_masked(mask: MaskPattern, x: i16, y: i16) -> bool {
+    match mask {
+        MaskPattern::Checkerboard => (x + y) % 2 == 0,
+        MaskPattern::HorizontalLines => y % 2 == 0,
+        MaskPattern::VerticalLines => x % 3 == 0,
+        MaskPattern::DiagonalLines => (x + y) % 3 == 0,
+        MaskPattern::LargeCheckerboard => (x / 3 + y / 2) % 2 == 0,
+        MaskPattern::Fields => (x * y) % 2 + (x * y) % 3 == 0,
+        MaskPattern::Diamonds => ((x * y) % 2 + (x * y) % 3) % 2 == 0,
+        MaskPattern::Meadow => ((x + y) % 2 + (x * y) % 3) % 2 == 0,
+    }
+}
+
+// undoes the block interleaving by letting the encoder interleave the codeword indices
+fn deinterleave(raw: &[u8], version: Version, ec_level: EcLevel) -> Result<Vec<u8>, String> {
+    let err = |e| format!("{:?}", e);
+    let length = Bits::new(version).max_len(ec_level).map_err(err)? / 8;
+    let low = (0..length).map(|i| i as u8).collect::<Vec<_>>();
+    let high = (0..length).map(|i| (i >> 8) as u8).collect::<Vec<_>>();
+    let (low, _) = construct_codewords(&low, version, ec_level).map_err(err)?;
+    let (high, _) = construct_codewords(&high, version, ec_level).map_err(err)?;
+
+    let mut data = vec![0; length];
+    for (position, (l, h)) in low.iter().zip(high.iter()).enumerate() {
+        data[usize::from(*h) << 8 | usize::from(*l)] = *raw
+            .get(position)
+            .ok_or_else(|| "the code is truncated".to_string())?;
+    }
+    Ok(data)
+}
+
+const ALPHANUMERIC: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+// reads the numeric, alphanumeric and byte segments, ECI and FNC1 markers are skipped
+fn parse_segments(data: &[u8], version: Version) -> Result<Vec<u8>, String> {
+    let mut reader = BitReader { data, position: 0 };
+    let size = match version {
+        Version::Normal(1..=9) => 0,
+        Version::Normal(10..=26) => 1,
+        _ => 2,
+    };
+    let mut out = Vec::new();
+    let mut fnc1 = false;
+
+    let truncated = || "the data ends in the middle of a segment".to_string();
+    while let Some(mode) = reader.read(4) {
+        match mode {
+            0b0000 => break,
+            0b0001 => {
+                let mut count = reader.read([10, 12, 14][size]).ok_or_else(truncated)?;
+                while count > 0 {
+                    let digits = count.min(3);
+                    let n = reader.read([4, 7, 10][digits - 1]).ok_or_else(truncated)?;
+                    out.extend(format!("{:01$}", n, digits).bytes());
+                    count -= digits;
+                }
+            }
+            0b0010 => {
+                let mut count = reader.read([9, 11, 13][size]).ok_or_else(truncated)?;
+                let mut text = Vec::new();
+                while count > 0 {
+                    if count >= 2 {
+                        let n = reader.read(11).ok_or_else(truncated)?;
+                        text.push(ALPHANUMERIC[(n / 45) % 45]);
+                        text.push(ALPHANUMERIC[n % 45]);
+                        count -= 2;
+                    } else {
+                        let n = reader.read(6).ok_or_else(truncated)?;
+                        text.push(ALPHANUMERIC[n % 45]);
+                        count -= 1;
+                    }
+                }
+                // in FNC1 mode a single '%' stands for the GS separator
+                if fnc1 {
+                    let text = String::from_utf8(text).unwrap();
+                    out.extend(
+                        text.replace("%%", "\u{0}")
+                            .replace('%', "\u{1d}")
+                            .replace('\u{0}', "%")
+                            .bytes(),
+                    );
+                } else {
+                    out.extend(text);
+                }
+            }
+            0b0100 => {
+                let count = reader.read([8, 16, 16][size]).ok_or_else(truncated)?;
+                for _ in 0..count {
+                    out.push(reader.read(8).ok_or_else(truncated)? as u8);
+                }
+            }
+            0b0111 => {
+                // the designator takes one to three bytes, flagged by its leading bits
+                let first = reader.read(8).ok_or_else(truncated)?;
+                let extra = if first & 0x80 == 0 {
+                    0
+                } else if first & 0x40 == 0 {
+                    8
+                } else {
+                    16
+                };
+                reader.read(extra).ok_or_else(truncated)?;
+            }
+            0b0101 => fnc1 = true,
+            0b1001 => {
+                reader.read(8).ok_or_else(truncated)?;
+                fnc1 = true;
+            }
+            other => return Err(format!("the segment mode {:04b} is not supported", other)),
+        }
+    }
+    Ok(out)
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn read(&mut self, count: usize) -> Option<usize> {
+        if self.position + count > self.data.len() * 8 {
+            return None;
+        }
+        let mut value = 0;
+        for _ in 0..count {
+            let bit = self.data[self.position / 8] >> (7 - self.position % 8) & 1;
+            value = (value << 1) | usize::from(bit);
+            self.position += 1;
+        }
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+    use qrcode::QrCode;
+
+    fn decode(code: &QrCode) -> Vec<u8> {
+        decode_image(&code.render::<Luma<u8>>().build()).unwrap()
+    }
+
+    #[test]
+    fn decodes_every_segment_mode() {
+        let payloads: [&[u8]; 4] = [
+            b"01234567890123456789",
+            b"HELLO WORLD $%*+-./:",
+            "gr\u{fc}\u{df}e, world".as_bytes(),
+            b"WIFI:S:home;T:WPA;P:0123456789;;",
+        ];
+        for payload in payloads.iter() {
+            for &level in &[EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H] {
+                let code = QrCode::with_error_correction_level(payload, level).unwrap();
+                assert_eq!(decode(&code), *payload);
+            }
+        }
+    }
+
+    #[test]
+    fn decodes_larger_versions() {
+        // versions 1-9, 10-26 and 27-40 use different sizes for the character counts
+        for &length in &[200, 800, 2000] {
+            let payload = (0..length)
+                .map(|i| b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabc"[i % 39])
+                .collect::<Vec<_>>();
+            let code = QrCode::with_error_correction_level(&payload, EcLevel::L).unwrap();
+            assert!(code.width() >= 45);
+            assert_eq!(decode(&code), payload);
+        }
+    }
+
+    #[test]
+    fn decodes_fnc1_separators() {
+        let mut bits = Bits::new(Version::Normal(2));
+        bits.push_fnc1_first_position().unwrap();
+        bits.push_numeric_data(b"0104012345678901").unwrap();
+        bits.push_alphanumeric_data(b"10AB%%1%21XYZ").unwrap();
+        bits.push_terminator(EcLevel::M).unwrap();
+        let code = QrCode::with_bits(bits, EcLevel::M).unwrap();
+        assert_eq!(decode(&code), b"010401234567890110AB%1\x1d21XYZ".to_vec());
+    }
+
+    #[test]
+    fn stray_images_are_rejected() {
+        // short runs in the corner make the modules too small for the bounding box
+        let mut image = GrayImage::from_pixel(41, 41, Luma([255]));
+        for i in 0..14 {
+            image.put_pixel(i, 0, Luma([0]));
+            image.put_pixel(0, i, Luma([0]));
+        }
+        image.put_pixel(40, 40, Luma([0]));
+        assert_eq!(decode_image(&image).unwrap_err(), "no QR-Code was found");
+    }
+}
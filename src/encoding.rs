@@ -0,0 +1,537 @@
+use flate2::read::{DeflateDecoder, ZlibDecoder};
+use flate2::write::{DeflateEncoder, ZlibEncoder};
+use flate2::Compression;
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+
+const BASE32_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+const BASE32HEX_ALPHABET: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUV";
+
+// RFC 4648 base32 without padding
+#[allow(dead_code)]
+pub fn base32_encode(data: &[u8]) -> String {
+    encode_base32(data, BASE32_ALPHABET)
+}
+
+// the "extended hex" base32 alphabet, also without padding
+#[allow(dead_code)]
+pub fn base32hex_encode(data: &[u8]) -> String {
+    encode_base32(data, BASE32HEX_ALPHABET)
+}
+
+fn encode_base32(data: &[u8], alphabet: &[u8]) -> String {
+    let mut out = String::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for &b in data {
+        buffer = (buffer << 8) | u32::from(b);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(alphabet[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(alphabet[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+    out
+}
+
+// decodes base32 leniently: case, spaces and padding are ignored
+#[allow(dead_code)]
+pub fn base32_decode(inp: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in inp.chars().filter(|c| !c.is_whitespace() && *c != '=') {
+        let v = BASE32_ALPHABET
+            .iter()
+            .position(|&a| a as char == c.to_ascii_uppercase())?;
+        buffer = (buffer << 5) | v as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+pub const BECH32_ALPHABET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+#[derive(Debug, PartialEq)]
+pub enum Bech32Variant {
+    Bech32,
+    Bech32m,
+}
+
+impl Bech32Variant {
+    fn constant(&self) -> u32 {
+        match *self {
+            Bech32Variant::Bech32 => 1,
+            Bech32Variant::Bech32m => 0x2bc8_30a3,
+        }
+    }
+}
+
+fn bech32_polymod(values: &[u8]) -> u32 {
+    const GENERATOR: [u32; 5] = [
+        0x3b6a_57b2,
+        0x2650_8e6d,
+        0x1ea1_19fa,
+        0x3d42_33dd,
+        0x2a14_62b3,
+    ];
+    values.iter().fold(1, |chk, &v| {
+        let top = chk >> 25;
+        let chk = ((chk & 0x01ff_ffff) << 5) ^ u32::from(v);
+        (0..5).fold(chk, |chk, i| {
+            if (top >> i) & 1 == 1 {
+                chk ^ GENERATOR[i]
+            } else {
+                chk
+            }
+        })
+    })
+}
+
+fn bech32_hrp_expand(hrp: &str) -> Vec<u8> {
+    let mut out: Vec<u8> = hrp.bytes().map(|b| b >> 5).collect();
+    out.push(0);
+    out.extend(hrp.bytes().map(|b| b & 31));
+    out
+}
+
+// encodes 5-bit groups with a lowercase human-readable part
+#[allow(dead_code)]
+pub fn bech32_encode(hrp: &str, data: &[u8], variant: &Bech32Variant) -> String {
+    let mut values = bech32_hrp_expand(hrp);
+    values.extend(data);
+    values.extend(&[0; 6]);
+    let polymod = bech32_polymod(&values) ^ variant.constant();
+
+    let mut out = hrp.to_string() + "1";
+    out.extend(data.iter().map(|&d| BECH32_ALPHABET[d as usize] as char));
+    out.extend((0..6).map(|i| BECH32_ALPHABET[((polymod >> (5 * (5 - i))) & 31) as usize] as char));
+    out
+}
+
+// splits a bech32 string into its lowercase human-readable part and 5-bit data groups
+#[allow(dead_code)]
+pub fn bech32_decode(inp: &str) -> Result<(String, Vec<u8>, Bech32Variant), String> {
+    if inp != inp.to_lowercase() && inp != inp.to_uppercase() {
+        return Err("bech32 strings must not mix upper and lower case".to_string());
+    }
+    let lower = inp.to_lowercase();
+    let sep = match lower.rfind('1') {
+        Some(i) if i > 0 && i + 7 <= lower.len() => i,
+        _ => return Err("the bech32 separator or checksum is missing".to_string()),
+    };
+    let hrp = &lower[..sep];
+    if !hrp.bytes().all(|b| (33..=126).contains(&b)) {
+        return Err("the human-readable part contains invalid characters".to_string());
+    }
+
+    let mut data = Vec::new();
+    for (i, c) in lower[sep + 1..].chars().enumerate() {
+        match BECH32_ALPHABET.iter().position(|&a| a as char == c) {
+            Some(v) => data.push(v as u8),
+            None => {
+                return Err(format!(
+                    "{:?} at position {} is not a bech32 character",
+                    c,
+                    sep + 2 + i
+                ))
+            }
+        }
+    }
+
+    let mut values = bech32_hrp_expand(hrp);
+    values.extend(&data);
+    let variant = match bech32_polymod(&values) {
+        1 => Bech32Variant::Bech32,
+        0x2bc8_30a3 => Bech32Variant::Bech32m,
+        _ => return Err("the bech32 checksum is invalid".to_string()),
+    };
+    data.truncate(data.len() - 6);
+    Ok((hrp.to_string(), data, variant))
+}
+
+// regroups bits, e.g. bytes into the 5-bit groups bech32 works with
+#[allow(dead_code)]
+pub fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Option<Vec<u8>> {
+    let mut acc = 0u32;
+    let mut bits = 0;
+    let mut out = Vec::new();
+    let max = (1 << to) - 1;
+    for &v in data {
+        if u32::from(v) >> from != 0 {
+            return None;
+        }
+        acc = (acc << from) | u32::from(v);
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            out.push(((acc >> bits) & max) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            out.push(((acc << (to - bits)) & max) as u8);
+        }
+    } else if bits >= from || ((acc << (to - bits)) & max) != 0 {
+        return None;
+    }
+    Some(out)
+}
+
+pub const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+#[allow(dead_code)]
+pub fn base58_decode(inp: &str) -> Option<Vec<u8>> {
+    let mut out: Vec<u8> = Vec::new();
+    for c in inp.bytes() {
+        let mut carry = BASE58_ALPHABET.iter().position(|&a| a == c)? as u32;
+        // multiply the little-endian big number by 58 and add the digit
+        for b in out.iter_mut() {
+            carry += u32::from(*b) * 58;
+            *b = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            out.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    // every leading '1' stands for a leading zero byte
+    out.extend(inp.bytes().take_while(|&c| c == b'1').map(|_| 0));
+    out.reverse();
+    Some(out)
+}
+
+const BASE64_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const BASE64URL_ALPHABET: &[u8] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+// RFC 4648 base64, the url-safe variant leaves out the padding
+#[allow(dead_code)]
+pub fn base64_encode(data: &[u8], url_safe: bool) -> String {
+    let alphabet = if url_safe {
+        BASE64URL_ALPHABET
+    } else {
+        BASE64_ALPHABET
+    };
+    let mut out = String::new();
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(alphabet[((n >> (18 - 6 * i)) & 63) as usize] as char);
+        }
+        if !url_safe {
+            out.extend(std::iter::repeat_n('=', 3 - chunk.len()));
+        }
+    }
+    out
+}
+
+// decodes both base64 alphabets, padding and whitespace are optional
+#[allow(dead_code)]
+pub fn base64_decode(inp: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    let trimmed = inp.trim_end_matches(|c: char| c == '=' || c.is_whitespace());
+    for c in trimmed.bytes().filter(|c| !c.is_ascii_whitespace()) {
+        let v = match c {
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => BASE64_ALPHABET[..62].iter().position(|&a| a == c)? as u32,
+        };
+        buffer = (buffer << 6) | v;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    // a single leftover character can not hold a whole byte
+    if bits >= 6 {
+        return None;
+    }
+    Some(out)
+}
+
+const BASE38_ALPHABET: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ-.";
+
+// Matter's base38: little-endian chunks of 3, 2 or 1 bytes become 5, 4 or 2 characters
+#[allow(dead_code)]
+pub fn base38_encode(data: &[u8]) -> String {
+    let mut out = String::new();
+    for chunk in data.chunks(3) {
+        let mut n = chunk
+            .iter()
+            .rev()
+            .fold(0u32, |n, &b| (n << 8) | u32::from(b));
+        let chars = [0, 2, 4, 5][chunk.len()];
+        for _ in 0..chars {
+            out.push(BASE38_ALPHABET[(n % 38) as usize] as char);
+            n /= 38;
+        }
+    }
+    out
+}
+
+#[derive(Debug)]
+pub enum Bencode<'a> {
+    Int(i64),
+    Bytes(&'a [u8]),
+    List(Vec<Bencode<'a>>),
+    // dictionary values keep their raw encoding, torrents hash the "info" value as is
+    Dict(Vec<(&'a [u8], Bencode<'a>, &'a [u8])>),
+}
+
+impl<'a> Bencode<'a> {
+    pub fn get(&self, key: &str) -> Option<&Bencode<'a>> {
+        self.get_raw(key).map(|(value, _)| value)
+    }
+
+    pub fn get_raw(&self, key: &str) -> Option<(&Bencode<'a>, &'a [u8])> {
+        match self {
+            Bencode::Dict(entries) => entries
+                .iter()
+                .find(|(k, _, _)| *k == key.as_bytes())
+                .map(|(_, v, raw)| (v, *raw)),
+            _ => None,
+        }
+    }
+}
+
+// real torrents nest a handful of levels, anything deeper would only exhaust the stack
+const BENCODE_MAX_DEPTH: usize = 64;
+
+// parses a complete bencoded value like a .torrent file
+#[allow(dead_code)]
+pub fn bencode_decode(data: &[u8]) -> Result<Bencode<'_>, String> {
+    match bencode_value(data, 0, 0)? {
+        (value, end) if end == data.len() => Ok(value),
+        _ => Err("there is trailing data after the bencoded value".to_string()),
+    }
+}
+
+fn bencode_value(data: &[u8], pos: usize, depth: usize) -> Result<(Bencode<'_>, usize), String> {
+    if depth >= BENCODE_MAX_DEPTH {
+        return Err(format!(
+            "the bencoded data is nested deeper than {} levels",
+            BENCODE_MAX_DEPTH
+        ));
+    }
+    let truncated = || "the bencoded data is truncated".to_string();
+    let find = |from: usize, end: u8| {
+        data[from..]
+            .iter()
+            .position(|&b| b == end)
+            .map(|i| from + i)
+            .ok_or_else(truncated)
+    };
+    let number = |from: usize, to: usize| {
+        std::str::from_utf8(&data[from..to])
+            .ok()
+            .and_then(|n| n.parse::<i64>().ok())
+            .ok_or_else(|| format!("invalid bencoded number at byte {}", from))
+    };
+
+    match data.get(pos).ok_or_else(truncated)? {
+        b'i' => {
+            let end = find(pos + 1, b'e')?;
+            Ok((Bencode::Int(number(pos + 1, end)?), end + 1))
+        }
+        b'l' => {
+            let mut items = Vec::new();
+            let mut pos = pos + 1;
+            while *data.get(pos).ok_or_else(truncated)? != b'e' {
+                let (item, end) = bencode_value(data, pos, depth + 1)?;
+                items.push(item);
+                pos = end;
+            }
+            Ok((Bencode::List(items), pos + 1))
+        }
+        b'd' => {
+            let mut entries = Vec::new();
+            let mut pos = pos + 1;
+            while *data.get(pos).ok_or_else(truncated)? != b'e' {
+                let key = match bencode_value(data, pos, depth + 1)? {
+                    (Bencode::Bytes(k), end) => {
+                        pos = end;
+                        k
+                    }
+                    _ => {
+                        return Err(format!(
+                            "dictionary keys have to be strings at byte {}",
+                            pos
+                        ))
+                    }
+                };
+                let (value, end) = bencode_value(data, pos, depth + 1)?;
+                entries.push((key, value, &data[pos..end]));
+                pos = end;
+            }
+            Ok((Bencode::Dict(entries), pos + 1))
+        }
+        b'0'..=b'9' => {
+            let colon = find(pos, b':')?;
+            let length = usize::try_from(number(pos, colon)?).map_err(|e| e.to_string())?;
+            let start = colon + 1;
+            let end = start.checked_add(length).ok_or_else(truncated)?;
+            let bytes = data.get(start..end).ok_or_else(truncated)?;
+            Ok((Bencode::Bytes(bytes), end))
+        }
+        other => Err(format!("unexpected {:?} at byte {}", *other as char, pos)),
+    }
+}
+
+const BASE45_ALPHABET: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+// RFC 9285 base45, which only uses characters of the QR alphanumeric mode
+#[allow(dead_code)]
+pub fn base45_encode(data: &[u8]) -> String {
+    let mut out = String::new();
+    for chunk in data.chunks(2) {
+        let mut n = chunk.iter().fold(0u32, |n, &b| (n << 8) | u32::from(b));
+        for _ in 0..=chunk.len() {
+            out.push(BASE45_ALPHABET[(n % 45) as usize] as char);
+            n /= 45;
+        }
+    }
+    out
+}
+
+#[allow(dead_code)]
+pub fn base45_decode(inp: &str) -> Option<Vec<u8>> {
+    let values = inp
+        .bytes()
+        .map(|c| {
+            BASE45_ALPHABET
+                .iter()
+                .position(|&a| a == c)
+                .map(|v| v as u32)
+        })
+        .collect::<Option<Vec<u32>>>()?;
+    let mut out = Vec::new();
+    for chunk in values.chunks(3) {
+        let n = chunk.iter().rev().fold(0, |n, &v| n * 45 + v);
+        match chunk.len() {
+            3 if n <= 0xffff => out.extend_from_slice(&[(n >> 8) as u8, n as u8]),
+            2 if n <= 0xff => out.push(n as u8),
+            _ => return None,
+        }
+    }
+    Some(out)
+}
+
+// the compression stage in front of the QR-Code, zlib adds a small header and a checksum to
+// the raw deflate stream
+#[allow(dead_code)]
+pub fn compress(data: &[u8], method: &str) -> Result<Vec<u8>, String> {
+    let compressed = match method {
+        "zlib" => {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+            encoder.write_all(data).and_then(|_| encoder.finish())
+        }
+        "deflate" => {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+            encoder.write_all(data).and_then(|_| encoder.finish())
+        }
+        other => return Err(format!("the compression {:?} is not supported", other)),
+    };
+    compressed.map_err(|e| format!("could not compress the payload: {}", e))
+}
+
+#[allow(dead_code)]
+pub fn decompress(data: &[u8], method: &str) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    let result = match method {
+        "zlib" => ZlibDecoder::new(data).read_to_end(&mut out),
+        "deflate" => DeflateDecoder::new(data).read_to_end(&mut out),
+        other => return Err(format!("the compression {:?} is not supported", other)),
+    };
+    result.map_err(|e| format!("could not decompress the payload: {}", e))?;
+    Ok(out)
+}
+
+// the text encoding stage, base45 and base32 stay in the alphanumeric mode of QR-Codes
+#[allow(dead_code)]
+pub fn encode_as(data: &[u8], encoding: &str) -> Result<String, String> {
+    match encoding {
+        "base45" => Ok(base45_encode(data)),
+        "base32" => Ok(base32_encode(data)),
+        "base64url" => Ok(base64_encode(data, true)),
+        other => Err(format!("the encoding {:?} is not supported", other)),
+    }
+}
+
+#[allow(dead_code)]
+pub fn decode_as(text: &str, encoding: &str) -> Result<Vec<u8>, String> {
+    let decoded = match encoding {
+        "base45" => base45_decode(text.trim_end_matches(['\r', '\n'])),
+        "base32" => base32_decode(text),
+        "base64url" => base64_decode(text),
+        other => return Err(format!("the encoding {:?} is not supported", other)),
+    };
+    decoded.ok_or_else(|| format!("the payload is not valid {}", encoding))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bencode_nesting_is_limited() {
+        let nested = |depth| format!("{}{}", "l".repeat(depth), "e".repeat(depth));
+        assert!(bencode_decode(nested(BENCODE_MAX_DEPTH).as_bytes()).is_ok());
+        assert!(bencode_decode(nested(BENCODE_MAX_DEPTH + 1).as_bytes()).is_err());
+        assert!(bencode_decode(nested(100_000).as_bytes()).is_err());
+        assert!(bencode_decode(b"18446744073709551615:x").is_err());
+    }
+
+    #[test]
+    fn base45_rfc_vectors() {
+        for (data, text) in [
+            (&b"AB"[..], "BB8"),
+            (b"Hello!!", "%69 VD92EX0"),
+            (b"base-45", "UJCLQE7W581"),
+            (b"ietf!", "QED8WEX0"),
+        ]
+        .iter()
+        {
+            assert_eq!(base45_encode(data), *text);
+            assert_eq!(base45_decode(text).unwrap(), *data);
+        }
+        // triples above 0xffff, pairs above 0xff, single characters and foreign characters
+        for invalid in ["GGW", ":::", "GG", "A", "aB8"].iter() {
+            assert_eq!(base45_decode(invalid), None);
+        }
+    }
+
+    #[test]
+    fn stages_round_trip() {
+        let data = b"WIFI:S:home;T:WPA;P:correct horse battery staple;;".repeat(4);
+        for method in ["zlib", "deflate"].iter() {
+            let compressed = compress(&data, method).unwrap();
+            assert!(compressed.len() < data.len());
+            assert_eq!(decompress(&compressed, method).unwrap(), data);
+        }
+        assert!(decompress(b"not compressed", "zlib").is_err());
+        assert!(compress(&data, "lzma").is_err());
+
+        let binary = (0..=255).collect::<Vec<u8>>();
+        for encoding in ["base45", "base32", "base64url"].iter() {
+            let text = encode_as(&binary, encoding).unwrap();
+            assert_eq!(decode_as(&text, encoding).unwrap(), binary);
+            assert_eq!(decode_as(&format!("{}\n", text), encoding).unwrap(), binary);
+        }
+        assert!(decode_as("not base45", "base45").is_err());
+    }
+}
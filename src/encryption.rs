@@ -0,0 +1,103 @@
+use crate::encoding::{base45_decode, base45_encode};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+// version 1 envelopes are "QRE1:" followed by base45(salt | nonce | ciphertext), so the whole
+// code fits into the alphanumeric mode
+const ENVELOPE_V1: &str = "QRE1:";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+// encrypts the payload with a key derived from the passphrase
+#[allow(dead_code)]
+pub fn encrypt_payload(payload: &str, passphrase: &str) -> Result<String, String> {
+    let mut random = [0u8; SALT_LEN + NONCE_LEN];
+    getrandom::getrandom(&mut random).map_err(|e| e.to_string())?;
+    let (salt, nonce) = random.split_at(SALT_LEN);
+
+    let cipher = derive_cipher(passphrase, salt)?;
+    // the version is authenticated as well, so envelopes can not be relabeled
+    let ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: payload.as_bytes(),
+                aad: ENVELOPE_V1.as_bytes(),
+            },
+        )
+        .map_err(|_| "the payload could not be encrypted".to_string())?;
+
+    let mut envelope = random.to_vec();
+    envelope.extend_from_slice(&ciphertext);
+    Ok(format!("{}{}", ENVELOPE_V1, base45_encode(&envelope)))
+}
+
+#[allow(dead_code)]
+pub fn decrypt_payload(envelope: &str, passphrase: &str) -> Result<String, String> {
+    let data = envelope
+        .trim()
+        .strip_prefix(ENVELOPE_V1)
+        .ok_or_else(|| "the text is not an encrypted qrterm payload".to_string())?;
+    let data = base45_decode(data)
+        .filter(|d| d.len() > SALT_LEN + NONCE_LEN)
+        .ok_or_else(|| "the encrypted payload is damaged".to_string())?;
+    let (salt, rest) = data.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+    let plaintext = derive_cipher(passphrase, salt)?
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: ENVELOPE_V1.as_bytes(),
+            },
+        )
+        .map_err(|_| "wrong passphrase or the payload was altered".to_string())?;
+    String::from_utf8(plaintext).map_err(|_| "the decrypted payload is not utf-8".to_string())
+}
+
+// Argon2id with 19 MiB and two passes, the parameters are fixed for version 1
+fn derive_cipher(passphrase: &str, salt: &[u8]) -> Result<ChaCha20Poly1305, String> {
+    let params = Params::new(19 * 1024, 2, 1, Some(32)).map_err(|e| e.to_string())?;
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| e.to_string())?;
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn envelopes_round_trip() {
+        let envelope = encrypt_payload("WIFI:S:home;P:secret;;", "correct horse").unwrap();
+        assert!(envelope.starts_with(ENVELOPE_V1));
+        assert!(!envelope.contains("secret"));
+        assert_eq!(
+            decrypt_payload(&envelope, "correct horse").unwrap(),
+            "WIFI:S:home;P:secret;;"
+        );
+        assert!(decrypt_payload(&envelope, "wrong horse").is_err());
+    }
+
+    #[test]
+    fn altered_envelopes_are_rejected() {
+        let envelope = encrypt_payload("hello", "passphrase").unwrap();
+        let mut data = base45_decode(&envelope[ENVELOPE_V1.len()..]).unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 1;
+        let tampered = format!("{}{}", ENVELOPE_V1, base45_encode(&data));
+        assert!(decrypt_payload(&tampered, "passphrase").is_err());
+
+        let truncated = format!(
+            "{}{}",
+            ENVELOPE_V1,
+            base45_encode(&data[..SALT_LEN + NONCE_LEN])
+        );
+        assert!(decrypt_payload(&truncated, "passphrase").is_err());
+        assert!(decrypt_payload("hello", "passphrase").is_err());
+    }
+}
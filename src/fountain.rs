@@ -0,0 +1,390 @@
+use crate::encoding::{base45_decode, base45_encode};
+use sha2::{Digest, Sha256};
+
+// every frame reads "QRF1:<id>:<file size>:<block size>:<seed>:<base45 data>", the seed decides
+// which blocks were xored into the data, so the receiver needs nothing else to decode it
+const FRAME_V1: &str = "QRF1";
+// a block has to fit into a single code, and the block count bounds what a forged frame can
+// make the decoder allocate
+const MAX_BLOCK_SIZE: usize = 2953;
+const MAX_BLOCKS: usize = 65536;
+
+// Luby Transform frames of a file, any slightly larger set than the number of blocks will do
+#[allow(dead_code)]
+pub struct FountainEncoder {
+    id: String,
+    size: usize,
+    block_size: usize,
+    blocks: Vec<Vec<u8>>,
+}
+
+#[allow(dead_code)]
+impl FountainEncoder {
+    pub fn new(data: &[u8], block_size: usize) -> Result<FountainEncoder, String> {
+        if block_size == 0 {
+            return Err("the block size has to be at least one byte".to_string());
+        }
+        if block_size > MAX_BLOCK_SIZE {
+            return Err(format!(
+                "the block size can be at most {} bytes",
+                MAX_BLOCK_SIZE
+            ));
+        }
+        if data.is_empty() {
+            return Err("the file is empty".to_string());
+        }
+        if data.len().div_ceil(block_size) > MAX_BLOCKS {
+            return Err(format!(
+                "the file would need more than {} blocks, pick a larger block size",
+                MAX_BLOCKS
+            ));
+        }
+        let blocks = data
+            .chunks(block_size)
+            .map(|b| {
+                let mut block = b.to_vec();
+                block.resize(block_size, 0);
+                block
+            })
+            .collect();
+        Ok(FountainEncoder {
+            id: file_id(data),
+            size: data.len(),
+            block_size,
+            blocks,
+        })
+    }
+
+    pub fn block_count(&self) -> usize {
+        self.blocks.len()
+    }
+
+    // the frame for a seed, seeds can be anything but counting up from 1 is the easiest
+    pub fn frame(&self, seed: u32) -> String {
+        let mut data = vec![0; self.block_size];
+        for i in block_indices(seed, self.blocks.len()) {
+            xor(&mut data, &self.blocks[i]);
+        }
+        format!(
+            "{}:{}:{}:{}:{}:{}",
+            FRAME_V1,
+            self.id,
+            self.size,
+            self.block_size,
+            seed,
+            base45_encode(&data)
+        )
+    }
+}
+
+// collects frames in any order and peels the blocks out of them
+#[allow(dead_code)]
+#[derive(Default)]
+pub struct FountainDecoder {
+    id: String,
+    size: usize,
+    block_size: usize,
+    blocks: Vec<Option<Vec<u8>>>,
+    pending: Vec<(Vec<usize>, Vec<u8>)>,
+}
+
+#[allow(dead_code)]
+impl FountainDecoder {
+    pub fn new() -> FountainDecoder {
+        FountainDecoder::default()
+    }
+
+    pub fn add_frame(&mut self, frame: &str) -> Result<(), String> {
+        let malformed = || {
+            format!(
+                "{:?} is not a qrterm stream frame",
+                frame.chars().take(24).collect::<String>()
+            )
+        };
+        let fields = frame.trim().splitn(6, ':').collect::<Vec<_>>();
+        if fields.len() != 6 || fields[0] != FRAME_V1 || fields[1].len() != 8 {
+            return Err(malformed());
+        }
+        let size = fields[2].parse::<usize>().map_err(|_| malformed())?;
+        let block_size = fields[3].parse::<usize>().map_err(|_| malformed())?;
+        let seed = fields[4].parse::<u32>().map_err(|_| malformed())?;
+        if block_size == 0
+            || block_size > MAX_BLOCK_SIZE
+            || size == 0
+            || size.div_ceil(block_size) > MAX_BLOCKS
+        {
+            return Err(malformed());
+        }
+        let data = base45_decode(fields[5])
+            .filter(|d| d.len() == block_size)
+            .ok_or_else(malformed)?;
+
+        if self.blocks.is_empty() {
+            self.id = fields[1].to_string();
+            self.size = size;
+            self.block_size = block_size;
+            self.blocks = vec![None; size.div_ceil(block_size)];
+        } else if self.id != fields[1] || self.size != size || self.block_size != block_size {
+            return Err(format!(
+                "the frames belong to different files ({} and {})",
+                self.id, fields[1]
+            ));
+        }
+
+        self.pending
+            .push((block_indices(seed, self.blocks.len()), data));
+        self.peel();
+        Ok(())
+    }
+
+    pub fn progress(&self) -> (usize, usize) {
+        (
+            self.blocks.iter().filter(|b| b.is_some()).count(),
+            self.blocks.len(),
+        )
+    }
+
+    pub fn is_complete(&self) -> bool {
+        !self.blocks.is_empty() && self.blocks.iter().all(Option::is_some)
+    }
+
+    // the decoded file, checked against the hash that names the stream
+    pub fn finish(mut self) -> Result<Vec<u8>, String> {
+        if !self.is_complete() {
+            self.eliminate();
+        }
+        let (decoded, total) = self.progress();
+        if total == 0 {
+            return Err("no frames were given".to_string());
+        }
+        if decoded < total {
+            return Err(format!(
+                "only {} of {} blocks could be decoded, more frames are needed",
+                decoded, total
+            ));
+        }
+        let mut data = self
+            .blocks
+            .into_iter()
+            .flatten()
+            .flatten()
+            .collect::<Vec<_>>();
+        data.truncate(self.size);
+        if file_id(&data) != self.id {
+            return Err("the decoded file does not match the hash of the stream".to_string());
+        }
+        Ok(data)
+    }
+
+    // removes known blocks from the pending frames until no frame is left with a single block
+    fn peel(&mut self) {
+        let mut progress = true;
+        while progress {
+            progress = false;
+            let blocks = &mut self.blocks;
+            self.pending.retain_mut(|(indices, data)| {
+                indices.retain(|&i| match &blocks[i] {
+                    Some(block) => {
+                        xor(data, block);
+                        false
+                    }
+                    None => true,
+                });
+                if indices.len() == 1 {
+                    blocks[indices[0]] = Some(data.clone());
+                    progress = true;
+                }
+                indices.len() > 1
+            });
+        }
+    }
+
+    // peeling stalls on small files, gaussian elimination over GF(2) gets by with fewer frames
+    fn eliminate(&mut self) {
+        let unknown = (0..self.blocks.len())
+            .filter(|&i| self.blocks[i].is_none())
+            .collect::<Vec<_>>();
+        let mut rows = self
+            .pending
+            .iter()
+            .map(|(indices, data)| {
+                let columns = unknown
+                    .iter()
+                    .map(|u| indices.contains(u))
+                    .collect::<Vec<_>>();
+                (columns, data.clone())
+            })
+            .collect::<Vec<_>>();
+
+        let mut pivots = Vec::new();
+        for column in 0..unknown.len() {
+            let rank = pivots.len();
+            let pivot = match (rank..rows.len()).find(|&r| rows[r].0[column]) {
+                Some(p) => p,
+                None => continue,
+            };
+            rows.swap(rank, pivot);
+            let (above, below) = rows.split_at_mut(rank);
+            let (pivot_row, below) = below.split_first_mut().unwrap();
+            for row in above.iter_mut().chain(below.iter_mut()) {
+                if row.0[column] {
+                    for (a, b) in row.0.iter_mut().zip(&pivot_row.0) {
+                        *a ^= b;
+                    }
+                    xor(&mut row.1, &pivot_row.1);
+                }
+            }
+            pivots.push(column);
+        }
+
+        // fully reduced rows with a single column left are solved blocks
+        for (rank, column) in pivots.into_iter().enumerate() {
+            if rows[rank].0.iter().filter(|&&c| c).count() == 1 {
+                self.blocks[unknown[column]] = Some(rows[rank].1.clone());
+            }
+        }
+    }
+}
+
+// the first 32 bits of the SHA-256 of the file
+fn file_id(data: &[u8]) -> String {
+    Sha256::digest(data)[..4]
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect()
+}
+
+fn xor(target: &mut [u8], source: &[u8]) {
+    for (t, s) in target.iter_mut().zip(source) {
+        *t ^= s;
+    }
+}
+
+// picks the degree from the robust soliton distribution and then that many distinct blocks,
+// sender and receiver derive the same choice from the seed
+fn block_indices(seed: u32, count: usize) -> Vec<usize> {
+    let mut random = SplitMix64(u64::from(seed));
+    let degree = robust_soliton_degree(count, random.next_f64());
+    let mut indices = Vec::with_capacity(degree);
+    while indices.len() < degree {
+        let i = (random.next_u64() % count as u64) as usize;
+        if !indices.contains(&i) {
+            indices.push(i);
+        }
+    }
+    indices
+}
+
+fn robust_soliton_degree(count: usize, sample: f64) -> usize {
+    if count == 1 {
+        return 1;
+    }
+    const C: f64 = 0.1;
+    const DELTA: f64 = 0.5;
+    let k = count as f64;
+    let r = C * (k / DELTA).ln() * k.sqrt();
+    let spike = ((k / r).floor() as usize).clamp(1, count);
+
+    let weights = (1..=count)
+        .map(|d| {
+            let ideal = if d == 1 {
+                1.0 / k
+            } else {
+                1.0 / (d as f64 * (d as f64 - 1.0))
+            };
+            let robust = if d < spike {
+                r / (d as f64 * k)
+            } else if d == spike {
+                r * (r / DELTA).ln() / k
+            } else {
+                0.0
+            };
+            ideal + robust.max(0.0)
+        })
+        .collect::<Vec<_>>();
+
+    let mut target = sample * weights.iter().sum::<f64>();
+    for (d, w) in weights.iter().enumerate() {
+        if target < *w {
+            return d + 1;
+        }
+        target -= w;
+    }
+    count
+}
+
+// a small, well known generator, the stream format depends on its exact output
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_file() -> Vec<u8> {
+        (0..3400u32).map(|i| (i * 7 % 251) as u8).collect()
+    }
+
+    #[test]
+    fn frames_decode_in_any_order() {
+        let data = test_file();
+        let encoder = FountainEncoder::new(&data, 100).unwrap();
+        assert_eq!(encoder.block_count(), 34);
+
+        // every other frame was missed and the rest arrived backwards
+        let mut decoder = FountainDecoder::new();
+        for seed in (1..=200).rev().step_by(2) {
+            decoder.add_frame(&encoder.frame(seed)).unwrap();
+        }
+        assert!(decoder.is_complete());
+        assert_eq!(decoder.finish().unwrap(), data);
+    }
+
+    #[test]
+    fn elimination_needs_fewer_frames_than_peeling() {
+        let data = test_file();
+        let encoder = FountainEncoder::new(&data, 100).unwrap();
+        let decoder_for = |frames| {
+            let mut decoder = FountainDecoder::new();
+            for seed in 1..=frames {
+                decoder.add_frame(&encoder.frame(seed)).unwrap();
+            }
+            decoder
+        };
+
+        assert!(decoder_for(60).is_complete());
+        let stalled = decoder_for(40);
+        assert!(!stalled.is_complete());
+        assert_eq!(stalled.finish().unwrap(), data);
+        assert!(decoder_for(30).finish().is_err());
+    }
+
+    #[test]
+    fn foreign_and_forged_frames_are_rejected() {
+        let first = FountainEncoder::new(&test_file(), 100).unwrap();
+        let second = FountainEncoder::new(b"another file", 100).unwrap();
+        let mut decoder = FountainDecoder::new();
+        decoder.add_frame(&first.frame(1)).unwrap();
+        assert!(decoder.add_frame(&second.frame(2)).is_err());
+
+        let mut decoder = FountainDecoder::new();
+        let huge = format!("QRF1:0123ABCD:{}:1:1:00", usize::MAX);
+        assert!(decoder.add_frame(&huge).is_err());
+        assert!(decoder.add_frame("QRF1:0123ABCD:10:0:1:").is_err());
+        assert!(FountainEncoder::new(&[0; 100], 0).is_err());
+        assert!(FountainEncoder::new(&[0; 100], 3000).is_err());
+    }
+}
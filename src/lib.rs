@@ -2,14 +2,20 @@
 extern crate lazy_static;
 extern crate clap;
 
-use image::Luma;
-use qrcode::{EcLevel, QrCode};
+use image::{ImageBuffer, Luma};
+use qrcode::bits::Bits;
+use qrcode::types::QrError;
+use qrcode::{EcLevel, QrCode, QrResult, Version};
 
 use std::io::prelude::*;
+use std::process::exit;
 
 use term::color;
 
+mod encoding;
+mod encryption;
 mod payloads;
+mod signing;
 
 pub const WIFI_COMMAND: &str = "wifi";
 pub const MAIL_COMMAND: &str = "mail";
@@ -22,7 +28,23 @@ pub const WHATSAPP_COMMAND: &str = "whatsapp";
 pub const URL_COMMAND: &str = "url";
 pub const BOOKMARK_COMMAND: &str = "bookmark";
 pub const BITCOIN_COMMAND: &str = "bitcoin";
-// const GIRO_COMMAND: &'static str = "giro";
+pub const GIRO_COMMAND: &str = "giro";
+pub const SWISSQR_COMMAND: &str = "swissqr";
+pub const OTP_COMMAND: &str = "otp";
+pub const ETHEREUM_COMMAND: &str = "ethereum";
+pub const LIGHTNING_COMMAND: &str = "lightning";
+pub const DPP_COMMAND: &str = "dpp";
+pub const MATTER_COMMAND: &str = "matter";
+pub const ESIM_COMMAND: &str = "esim";
+pub const WIREGUARD_COMMAND: &str = "wireguard";
+pub const EMV_COMMAND: &str = "emv";
+pub const PIX_COMMAND: &str = "pix";
+pub const SPAYD_COMMAND: &str = "spayd";
+pub const PAYBYSQUARE_COMMAND: &str = "paybysquare";
+pub const OPENPGP_COMMAND: &str = "openpgp";
+pub const SSH_COMMAND: &str = "ssh";
+pub const MAGNET_COMMAND: &str = "magnet";
+pub const GS1_COMMAND: &str = "gs1";
 // const CALENDAR_COMMAND: &'static str = "calendar";
 // const CONTACT_COMMAND: &'static str = "contact";
 
@@ -35,6 +57,14 @@ pub struct Parameters {
     pub input: String,
     pub completions: Completions,
     pub command: String,
+    pub swiss_cross: bool,
+    pub secrets: Vec<String>,
+    pub gs1: bool,
+    pub sign_key: Option<String>,
+    pub sign_expires: Option<u64>,
+    pub passphrase: Option<String>,
+    pub compress: Option<String>,
+    pub encode: Option<String>,
 }
 
 impl Default for Parameters {
@@ -54,6 +84,14 @@ impl Parameters {
             input: "".to_string(),
             completions: Completions::new(),
             command: "".to_string(),
+            swiss_cross: false,
+            secrets: Vec::new(),
+            gs1: false,
+            sign_key: None,
+            sign_expires: None,
+            passphrase: None,
+            compress: None,
+            encode: None,
         }
     }
 
@@ -67,6 +105,14 @@ impl Parameters {
             input: "".to_string(),
             completions: Completions::new(),
             command: "".to_string(),
+            swiss_cross: false,
+            secrets: Vec::new(),
+            gs1: false,
+            sign_key: None,
+            sign_expires: None,
+            passphrase: None,
+            compress: None,
+            encode: None,
         }
     }
 
@@ -78,19 +124,96 @@ impl Parameters {
     - write integration tests for edge case inputs
     */
     pub fn generate(&self) {
-        //TODO: catch the possible errors
-        let code = QrCode::with_error_correction_level(&self.payload, self.error).unwrap();
+        // encrypted codes only ever show the envelope, never the plaintext
+        let payload = match &self.passphrase {
+            Some(p) => match encryption::encrypt_payload(&self.payload, p) {
+                Ok(e) => e,
+                Err(e) => {
+                    eprintln!("Could not encrypt the payload: {}", e);
+                    exit(1);
+                }
+            },
+            None => self.payload.clone(),
+        };
+
+        // signed codes carry their Ed25519 signature behind the payload
+        let payload = match &self.sign_key {
+            Some(key) => match signing::sign_payload(&payload, key, self.sign_expires) {
+                Ok(s) => s,
+                Err(e) => {
+                    eprintln!("Could not sign the payload: {}", e);
+                    exit(1);
+                }
+            },
+            None => payload,
+        };
+
+        // the compression and text encoding stages run last, so they also cover envelopes
+        let mut data = payload.clone().into_bytes();
+        let mut stages = Vec::new();
+        if let Some(method) = &self.compress {
+            let compressed = encoding::compress(&data, method).unwrap_or_else(|e| {
+                eprintln!("Could not compress the payload: {}", e);
+                exit(1);
+            });
+            stages.push(stage_report(
+                &format!("{} compression", method),
+                &data,
+                &compressed,
+                self.error,
+            ));
+            data = compressed;
+        }
+        if let Some(name) = &self.encode {
+            let encoded = encoding::encode_as(&data, name).unwrap_or_else(|e| {
+                eprintln!("Could not encode the payload: {}", e);
+                exit(1);
+            });
+            stages.push(stage_report(
+                &format!("{} encoding", name),
+                &data,
+                encoded.as_bytes(),
+                self.error,
+            ));
+            data = encoded.into_bytes();
+        }
+
+        let code = if self.gs1 {
+            gs1_code(&data, self.error)
+        } else {
+            QrCode::with_error_correction_level(&data, self.error)
+        };
+        let code = code.unwrap_or_else(|e| {
+            eprintln!("Could not create the QR-Code: {}", e);
+            exit(1);
+        });
 
         // are we drawing to the terminal or to a file?
-        if self.output.len() == 1 {
-            save(&code, self.safe_zone, &self.output);
+        if !self.output.is_empty() {
+            save(&code, self.safe_zone, self.swiss_cross, &self.output);
         } else {
-            draw(&code, self.safe_zone)
+            draw(&code, self.safe_zone, self.swiss_cross)
+        }
+
+        for stage in &stages {
+            println!("{}", stage);
         }
 
         // shall we also print the payload to the screen?
+        // secrets like private keys are only ever part of the code itself, they can only be
+        // found in the text, so a compressed or encoded payload is shown before those stages
         if !self.payload.is_empty() {
-            println!("{:?}", self.payload);
+            let shown = if self.secrets.is_empty() {
+                String::from_utf8_lossy(&data).to_string()
+            } else {
+                if !stages.is_empty() {
+                    println!("The payload before compression and encoding:");
+                }
+                self.secrets
+                    .iter()
+                    .fold(payload, |p, s| p.replace(s.as_str(), "(hidden)"))
+            };
+            println!("{:?}", shown);
         }
     }
 }
@@ -114,10 +237,61 @@ impl Default for Completions {
     }
 }
 
+// GS1 element strings start with the FNC1 mode indicator, the GS characters inside stand for FNC1
+fn gs1_code(payload: &[u8], error: EcLevel) -> QrResult<QrCode> {
+    for version in 1..=40 {
+        let mut bits = Bits::new(Version::Normal(version));
+        bits.push_fnc1_first_position()?;
+        // a literal '%' would be read as FNC1 inside alphanumeric segments
+        let pushed = if payload.contains(&b'%') {
+            bits.push_byte_data(payload)
+        } else {
+            bits.push_optimal_data(payload)
+        };
+        if pushed.and_then(|_| bits.push_terminator(error)).is_ok() {
+            return QrCode::with_bits(bits, error);
+        }
+    }
+    Err(QrError::DataTooLong)
+}
+
+// how many bytes and modules a pipeline stage saved, negative numbers mean it cost space
+fn stage_report(stage: &str, before: &[u8], after: &[u8], error: EcLevel) -> String {
+    let modules = |data: &[u8]| {
+        QrCode::with_error_correction_level(data, error)
+            .ok()
+            .map(|c| c.width() as i64 * c.width() as i64)
+    };
+    let bytes = before.len() as i64 - after.len() as i64;
+    match (modules(before), modules(after)) {
+        (Some(b), Some(a)) => format!(
+            "The {} saved {} bytes ({} -> {}) and {} modules ({} -> {})",
+            stage,
+            bytes,
+            before.len(),
+            after.len(),
+            b - a,
+            b,
+            a
+        ),
+        _ => format!(
+            "The {} saved {} bytes ({} -> {})",
+            stage,
+            bytes,
+            before.len(),
+            after.len()
+        ),
+    }
+}
+
 // save to a file at the path
-fn save(code: &QrCode, safe: bool, path: &str) {
+fn save(code: &QrCode, safe: bool, swiss_cross: bool, path: &str) {
     // render to a image struct
-    let image = code.render::<Luma<u8>>().quiet_zone(safe).build();
+    let mut image = code.render::<Luma<u8>>().quiet_zone(safe).build();
+
+    if swiss_cross {
+        draw_swiss_cross(&mut image, code.width(), safe);
+    }
 
     // save the image
     match image.save(path) {
@@ -133,10 +307,57 @@ fn save(code: &QrCode, safe: bool, path: &str) {
     };
 }
 
+// overlay the 7x7 mm Swiss cross that a 46x46 mm QR-bill code carries in its center
+fn draw_swiss_cross(image: &mut ImageBuffer<Luma<u8>, Vec<u8>>, modules: usize, safe: bool) {
+    // the renderer adds four modules of quiet zone on every side
+    let total = if safe { modules + 8 } else { modules };
+    let code_size = image.width() * modules as u32 / total as u32;
+    let center = image.width() / 2;
+
+    let outer = code_size * 7 / 46;
+    let inner = code_size * 6 / 46;
+    // the cross itself follows the proportions of the Swiss flag
+    let span = inner * 20 / 32;
+    let arm = inner * 6 / 32;
+
+    let within = |v: u32, size: u32| v + size / 2 >= center && v < center + (size - size / 2);
+    for y in 0..image.height() {
+        for x in 0..image.width() {
+            if !within(x, outer) || !within(y, outer) {
+                continue;
+            }
+            let is_cross =
+                (within(x, span) && within(y, arm)) || (within(x, arm) && within(y, span));
+            let is_white = !within(x, inner) || !within(y, inner) || is_cross;
+            image.put_pixel(x, y, Luma([if is_white { 255 } else { 0 }]));
+        }
+    }
+}
+
 // draw to the terminal
-fn draw(code: &QrCode, safe: bool) {
+fn draw(code: &QrCode, safe: bool, swiss_cross: bool) {
     // get "bit" array
-    let bit_array = code.to_colors();
+    let mut bit_array = code.to_colors();
+
+    // the cross is drawn at module resolution, which is as fine as the terminal gets anyway
+    if swiss_cross {
+        let mut image = code
+            .render::<Luma<u8>>()
+            .quiet_zone(false)
+            .module_dimensions(1, 1)
+            .build();
+        draw_swiss_cross(&mut image, code.width(), false);
+        bit_array = image
+            .pixels()
+            .map(|p| {
+                if p.data[0] < 128 {
+                    qrcode::Color::Dark
+                } else {
+                    qrcode::Color::Light
+                }
+            })
+            .collect();
+    }
 
     // get the terminal output pipe
     let mut t = term::stdout().unwrap();
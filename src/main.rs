@@ -6,11 +6,24 @@ extern crate clap;
 extern crate qrterm;
 
 use clap::{App, AppSettings, Arg, Shell, SubCommand};
-use qrcode::EcLevel;
+use qrcode::{EcLevel, QrCode};
+use regex::Regex;
 use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
 use std::process::exit;
+use std::thread;
+use std::time::Duration;
+use std::time::{SystemTime, UNIX_EPOCH};
 
+mod backup;
+mod decode;
+mod encoding;
+mod encryption;
+mod fountain;
 mod payloads;
+mod signing;
+mod wifi_import;
 
 fn main() {
     // match all input args
@@ -41,6 +54,329 @@ fn main() {
         exit(0);
     }
 
+    // keygen and verify work on signatures and dont generate a qr-code either
+    if let Some(sub) = matches.subcommand_matches("keygen") {
+        let path = sub.value_of("key_file").unwrap();
+        let (private, public) =
+            or_exit_with("Could not create the key", signing::generate_signing_key());
+        or_exit_with(
+            "Could not create the key",
+            write_private_file(path, private.as_bytes()),
+        );
+        let public_path = format!("{}.pub", path);
+        or_exit_with(
+            "Could not create the key",
+            fs::write(&public_path, &public).map_err(|e| e.to_string()),
+        );
+        println!("Private key written to: {:?}", path);
+        println!("Public key written to: {:?}", public_path);
+        exit(0);
+    }
+    if let Some(sub) = matches.subcommand_matches("verify") {
+        let key = sub.value_of("pubkey").unwrap();
+        let key = fs::read_to_string(key).unwrap_or_else(|_| key.to_string());
+        let signed = match sub.value_of("signed") {
+            Some(s) => s.to_string(),
+            None => {
+                let mut s = String::new();
+                std::io::stdin().read_to_string(&mut s).unwrap();
+                s.trim_end_matches('\n').to_string()
+            }
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        match signing::verify_payload(&signed, &key, now) {
+            Ok((payload, expires)) => {
+                println!("The signature is valid");
+                if let Some(e) = expires {
+                    println!("It expires at {}", signing::format_time(e));
+                }
+                println!("{:?}", payload);
+                exit(0);
+            }
+            Err(e) => {
+                eprintln!("Invalid payload: {}", e);
+                exit(1);
+            }
+        }
+    }
+
+    if let Some(sub) = matches.subcommand_matches("decrypt") {
+        let envelope = match sub.value_of("envelope") {
+            Some(e) => e.to_string(),
+            None => {
+                let mut e = String::new();
+                std::io::stdin().read_to_string(&mut e).unwrap();
+                e
+            }
+        };
+        let passphrase = or_exit_with("Could not decrypt the payload", read_passphrase(false));
+        match encryption::decrypt_payload(&envelope, &passphrase) {
+            Ok(payload) => {
+                println!("{}", payload);
+                exit(0);
+            }
+            Err(e) => {
+                eprintln!("Could not decrypt the payload: {}", e);
+                exit(1);
+            }
+        }
+    }
+
+    // paper backups write and read a whole set of codes
+    if let Some(sub) = matches.subcommand_matches("backup") {
+        reject_options(&matches, "backup", &PAYLOAD_OPTIONS);
+        let path = sub.value_of("file").unwrap();
+        let data = fs::read(path).unwrap_or_else(|e| {
+            eprintln!("Could not read {:?}: {}", path, e);
+            exit(1)
+        });
+        let chunk_size = value_t!(sub, "chunk_size", usize).unwrap_or_else(|e| e.exit());
+        let file_name = Path::new(path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string());
+        let chunks = or_exit_with(
+            "Could not create the backup",
+            backup::backup_chunks(&file_name, &data, chunk_size),
+        );
+        let longest = chunks.iter().max_by_key(|c| c.len()).unwrap();
+        or_exit_with(
+            "Could not create the backup",
+            check_capacity(longest, &matches, "--chunk-size"),
+        );
+
+        let dir = match sub.value_of("dir") {
+            Some(d) => d.to_string(),
+            None => format!("{}-backup", path),
+        };
+        or_exit_with(
+            "Could not create the backup",
+            fs::create_dir_all(&dir).map_err(|e| e.to_string()),
+        );
+        let mut images = Vec::new();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let image = format!("chunk-{:02}.png", i);
+            let mut params = qrterm::Parameters::new_with_data(chunk);
+            params.error = error_level(&matches);
+            params.safe_zone = matches.occurrences_of("safe_zone") == 0;
+            params.output = Path::new(&dir).join(&image).to_string_lossy().to_string();
+            params.generate();
+            images.push(image);
+        }
+        let index = Path::new(&dir).join("index.html");
+        or_exit_with(
+            "Could not create the backup",
+            fs::write(&index, backup_page(&file_name, data.len(), &images))
+                .map_err(|e| format!("could not write {:?}: {}", index, e)),
+        );
+        println!("Printable page written to: {:?}", index);
+        exit(0);
+    }
+    if let Some(sub) = matches.subcommand_matches("restore") {
+        reject_options(&matches, "restore", &PAYLOAD_OPTIONS);
+        reject_options(&matches, "restore", &["safe_zone"]);
+        let mut chunks = Vec::new();
+        match sub.values_of("chunks") {
+            Some(inputs) => {
+                for input in inputs {
+                    chunks.extend(or_exit_with(
+                        "Could not restore the backup",
+                        read_chunks(input),
+                    ));
+                }
+            }
+            None => {
+                let mut s = String::new();
+                std::io::stdin().read_to_string(&mut s).unwrap();
+                chunks.extend(s.lines().map(str::to_string));
+            }
+        }
+        let (file_name, data) = match backup::restore_chunks(&chunks) {
+            Ok(r) => r,
+            Err(e) => {
+                eprintln!("Could not restore the backup: {}", e);
+                exit(1);
+            }
+        };
+        // the name in the manifest is never trusted to point outside the current directory
+        let path = match sub.value_of("to") {
+            Some(p) => p.to_string(),
+            None => Path::new(&file_name)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| "restored".to_string()),
+        };
+        if let Err(e) = write_private_file(&path, &data) {
+            eprintln!("Could not restore the backup: {}", e);
+            exit(1);
+        }
+        println!("Restored {} bytes to: {:?}", data.len(), path);
+        exit(0);
+    }
+
+    // streams cycle through fountain coded frames, the receiver needs any big enough subset
+    if let Some(sub) = matches.subcommand_matches("stream") {
+        reject_options(&matches, "stream", &PAYLOAD_OPTIONS);
+        let path = sub.value_of("file").unwrap();
+        let data = fs::read(path).unwrap_or_else(|e| {
+            eprintln!("Could not read {:?}: {}", path, e);
+            exit(1)
+        });
+        let block_size = value_t!(sub, "block_size", usize).unwrap_or_else(|e| e.exit());
+        let fps = value_t!(sub, "fps", f64).unwrap_or_else(|e| e.exit());
+        if fps.is_nan() || fps <= 0.0 {
+            or_exit_with::<()>(
+                "Could not create the stream",
+                Err("the frame rate has to be above zero".to_string()),
+            );
+        }
+        let encoder = or_exit_with(
+            "Could not create the stream",
+            fountain::FountainEncoder::new(&data, block_size),
+        );
+        let blocks = encoder.block_count();
+        // the largest seed makes the longest frame
+        or_exit_with(
+            "Could not create the stream",
+            check_capacity(&encoder.frame(u32::MAX), &matches, "--block-size"),
+        );
+
+        if let Some(dir) = sub.value_of("dir") {
+            // a few more frames than blocks are needed, twice as many leaves plenty of room
+            let frames = match sub.value_of("frames") {
+                Some(_) => value_t!(sub, "frames", u32).unwrap_or_else(|e| e.exit()),
+                None => blocks as u32 * 2 + 10,
+            };
+            or_exit_with(
+                "Could not create the stream",
+                fs::create_dir_all(dir).map_err(|e| e.to_string()),
+            );
+            for seed in 1..=frames {
+                let mut params = qrterm::Parameters::new_with_data(&encoder.frame(seed));
+                params.error = error_level(&matches);
+                params.safe_zone = matches.occurrences_of("safe_zone") == 0;
+                params.output = Path::new(dir)
+                    .join(format!("frame-{:04}.png", seed))
+                    .to_string_lossy()
+                    .to_string();
+                params.generate();
+            }
+            exit(0);
+        }
+
+        let frames = sub
+            .value_of("frames")
+            .map(|_| value_t!(sub, "frames", u32).unwrap_or_else(|e| e.exit()));
+        // clear the screen once, then draw every frame over the last one
+        print!("\x1b[2J");
+        let mut seed = 1;
+        while frames.is_none_or(|f| seed <= f) {
+            print!("\x1b[H");
+            let mut params = qrterm::Parameters::new_with_data(&encoder.frame(seed));
+            params.error = error_level(&matches);
+            params.safe_zone = matches.occurrences_of("safe_zone") == 0;
+            params.generate();
+            println!(
+                "Frame {} of {} blocks, stop with Ctrl-C once the file was received",
+                seed, blocks
+            );
+            std::io::stdout().flush().unwrap();
+            thread::sleep(Duration::from_secs_f64(1.0 / fps));
+            seed = seed.wrapping_add(1);
+        }
+        exit(0);
+    }
+    if let Some(sub) = matches.subcommand_matches("unstream") {
+        reject_options(&matches, "unstream", &PAYLOAD_OPTIONS);
+        reject_options(&matches, "unstream", &["safe_zone"]);
+        let dir = sub.value_of("dir").unwrap();
+        let mut entries = fs::read_dir(dir)
+            .and_then(|d| {
+                d.map(|e| e.map(|e| e.path()))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .unwrap_or_else(|e| {
+                eprintln!("Could not read {:?}: {}", dir, e);
+                exit(1)
+            });
+        entries.sort();
+
+        let mut decoder = fountain::FountainDecoder::new();
+        let mut unreadable = 0;
+        for entry in entries.iter().filter(|e| is_image(e)) {
+            let frame = decode::decode_image_file(&entry.to_string_lossy())
+                .and_then(|f| String::from_utf8(f).map_err(|_| "not a frame".to_string()));
+            match frame.and_then(|f| decoder.add_frame(&f)) {
+                Ok(()) if decoder.is_complete() => break,
+                Ok(()) => {}
+                Err(_) => unreadable += 1,
+            }
+        }
+        if unreadable > 0 {
+            eprintln!("Skipped {} images that hold no readable frame", unreadable);
+        }
+        let data = match decoder.finish() {
+            Ok(d) => d,
+            Err(e) => {
+                eprintln!("Could not decode the stream: {}", e);
+                exit(1);
+            }
+        };
+        match sub.value_of("to") {
+            Some(path) => {
+                if let Err(e) = write_private_file(path, &data) {
+                    eprintln!("Could not decode the stream: {}", e);
+                    exit(1);
+                }
+                println!("Decoded {} bytes to: {:?}", data.len(), path);
+            }
+            None => std::io::stdout().write_all(&data).unwrap(),
+        }
+        exit(0);
+    }
+
+    // reverses --encode and --compress, in the opposite order they were applied in
+    if let Some(sub) = matches.subcommand_matches("decode") {
+        if !matches.is_present("encode") && !matches.is_present("compress") {
+            eprintln!("Could not decode the payload: pass the --encode or --compress options the code was made with");
+            exit(1);
+        }
+        let data = match sub.value_of("encoded") {
+            Some(input) if is_image(Path::new(input)) => {
+                or_exit_with("Could not read the image", decode::decode_image_file(input))
+            }
+            Some(input) => input.as_bytes().to_vec(),
+            None => {
+                let mut d = Vec::new();
+                std::io::stdin().read_to_end(&mut d).unwrap();
+                d
+            }
+        };
+        let data = match matches.value_of("encode") {
+            Some(name) => String::from_utf8(data)
+                .map_err(|_| format!("the payload is not valid {}", name))
+                .and_then(|text| encoding::decode_as(&text, name)),
+            None => Ok(data),
+        };
+        let data = match matches.value_of("compress") {
+            Some(method) => data.and_then(|d| encoding::decompress(&d, method)),
+            None => data,
+        };
+        match data {
+            Ok(d) => {
+                std::io::stdout().write_all(&d).unwrap();
+                exit(0);
+            }
+            Err(e) => {
+                eprintln!("Could not decode the payload: {}", e);
+                exit(1);
+            }
+        }
+    }
+
     let mut params = qrterm::Parameters::new();
 
     // write the completions if they were requested, then exit and dont print any qr-code
@@ -58,19 +394,72 @@ fn main() {
     // deduce the string payload
     params.payload = get_payload(&matches);
 
+    // private keys are hidden when the payload is printed
+    if matches.subcommand_name() == Some(qrterm::WIREGUARD_COMMAND) {
+        params.secrets = payloads::wireguard_secrets(&params.payload);
+    }
+    if let Some(sub) = matches.subcommand_matches(qrterm::GS1_COMMAND) {
+        params.gs1 = sub.is_present("element_string");
+        // FNC1 codes are read by GS1 scanners, which know nothing of the binary stages
+        if params.gs1 {
+            reject_options(&matches, "--element-string", &["compress", "encode"]);
+        }
+    }
+
     // should we draw a white border (safe zone) around the code?
     params.safe_zone = match matches.occurrences_of("safe_zone") {
         0 => true,
         _ => false,
     };
 
+    // shall the payload be encrypted?
+    if matches.is_present("encrypt") {
+        params.passphrase = Some(or_exit(read_passphrase(true)));
+    }
+
+    // binary friendly stages in front of the qr-code
+    params.compress = matches.value_of("compress").map(str::to_string);
+    params.encode = matches.value_of("encode").map(str::to_string);
+
+    // shall the payload be signed?
+    if let Some(path) = matches.value_of("sign") {
+        let key = or_exit(
+            fs::read_to_string(path).map_err(|e| format!("could not read {:?}: {}", path, e)),
+        );
+        or_exit(signing::parse_private_key(&key));
+        params.sign_key = Some(key);
+        if let Some(e) = matches.value_of("expires") {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            params.sign_expires = Some(or_exit(signing::parse_expiry(e, now)));
+        }
+    }
+
     // what error level can we expect? defaults to "H"
-    params.error = match matches.value_of("error").unwrap() {
-        "L" => EcLevel::L,
-        "M" => EcLevel::M,
-        "Q" => EcLevel::Q,
-        &_ => EcLevel::H,
+    params.error = error_level(&matches);
+
+    // some payload standards mandate their own error level
+    let mandated = match matches.subcommand_name() {
+        Some(qrterm::GIRO_COMMAND) => Some(("GiroCodes", EcLevel::M)),
+        Some(qrterm::SWISSQR_COMMAND) => {
+            params.swiss_cross = true;
+            Some(("Swiss QR-bills", EcLevel::M))
+        }
+        _ => None,
     };
+    if let Some((standard, level)) = mandated {
+        if matches.occurrences_of("error") > 0 && params.error != level {
+            println!(
+                "{} always use error correction level {:?}, ignoring -e {}",
+                standard,
+                level,
+                matches.value_of("error").unwrap()
+            );
+        }
+        params.error = level;
+    }
 
     // What outputs are there
 
@@ -82,20 +471,125 @@ fn main() {
     params.generate();
 }
 
+fn error_level(matches: &clap::ArgMatches<'_>) -> EcLevel {
+    match matches.value_of("error").unwrap() {
+        "L" => EcLevel::L,
+        "M" => EcLevel::M,
+        "Q" => EcLevel::Q,
+        &_ => EcLevel::H,
+    }
+}
+
+// backups and streams write codes of their own format, these options only apply to one payload
+const PAYLOAD_OPTIONS: [&str; 5] = ["output", "sign", "encrypt", "compress", "encode"];
+
+fn reject_options(matches: &clap::ArgMatches<'_>, command: &str, options: &[&str]) {
+    let given = options
+        .iter()
+        .filter(|o| matches.is_present(o))
+        .map(|o| format!("--{}", o.replace('_', "-")))
+        .collect::<Vec<_>>();
+    if !given.is_empty() {
+        eprintln!("{} can not be used with {}", given.join(", "), command);
+        exit(1);
+    }
+}
+
+// every code of a backup or stream has to fit, which is checked before anything is written
+fn check_capacity(
+    longest: &str,
+    matches: &clap::ArgMatches<'_>,
+    option: &str,
+) -> Result<(), String> {
+    let error = error_level(matches);
+    QrCode::with_error_correction_level(longest, error)
+        .map(|_| ())
+        .map_err(|_| {
+            format!(
+                "the codes do not fit into a QR-Code at error correction level {:?}, pick a smaller {}",
+                error, option
+            )
+        })
+}
+
 // deduces wich kind of string we are going to encode
 fn get_payload(matches: &clap::ArgMatches<'_>) -> String {
     if let Some(sub) = matches.subcommand_matches(qrterm::WIFI_COMMAND) {
+        // saved networks bring their own settings, so the password never touches the shell
+        if let Some(path) = sub.value_of("from") {
+            let config = or_exit(wifi_import::read_wifi_config(path, sub.value_of("ssid")));
+            let eap = config.eap_method.as_ref().map(|method| payloads::WifiEap {
+                method,
+                identity: config.identity.as_deref().unwrap_or(""),
+                anonymous_identity: config.anonymous_identity.as_deref(),
+                phase2: config.phase2.as_deref(),
+            });
+            return or_exit(payloads::wifi_string(
+                &config.ssid,
+                &config.password,
+                &config.mode,
+                config.hidden,
+                &payloads::WifiOptions {
+                    eap,
+                    ..Default::default()
+                },
+            ));
+        }
+
         let auth = match sub.value_of("mode") {
             Some("WEP") => payloads::Authentication::WEP,
             Some("WPA") => payloads::Authentication::WPA,
+            Some("SAE") => payloads::Authentication::SAE,
+            Some("WPA2-EAP") => payloads::Authentication::WPA2_EAP,
             _ => payloads::Authentication::nopass,
         };
-        payloads::wifi_string(
+        let eap = sub.value_of("eap_method").map(|method| payloads::WifiEap {
+            method,
+            identity: sub.value_of("identity").unwrap_or(""),
+            anonymous_identity: sub.value_of("anonymous_identity"),
+            phase2: sub.value_of("phase2"),
+        });
+
+        // a fresh passphrase is shown exactly once, next to the code that carries it
+        let generated = if sub.is_present("generate_password") {
+            if sub.value_of("pwd").is_some_and(|p| p != "-") {
+                or_exit::<()>(Err(
+                    "--generate-password replaces PWD, pass - in its place".to_string()
+                ));
+            }
+            let is_wpa =
+                auth == payloads::Authentication::WPA || auth == payloads::Authentication::SAE;
+            if !is_wpa {
+                or_exit::<()>(Err("generated passwords are meant for WPA or SAE".into()));
+            }
+            let charset = match sub.value_of("charset") {
+                Some("readable") => payloads::PasswordCharset::Readable,
+                Some("symbols") => payloads::PasswordCharset::Symbols,
+                _ => payloads::PasswordCharset::Alphanumeric,
+            };
+            let length = match sub.value_of("length") {
+                Some(_) => value_t!(sub, "length", usize).unwrap_or_else(|e| e.exit()),
+                None => 20,
+            };
+            let password = or_exit(payloads::wifi_generate_password(length, &charset));
+            show_generated("password", &password, matches);
+            Some(password)
+        } else {
+            None
+        };
+        or_exit(payloads::wifi_string(
             sub.value_of("ssid").unwrap(),
-            sub.value_of("pwd").unwrap(),
+            generated
+                .as_deref()
+                .unwrap_or_else(|| sub.value_of("pwd").unwrap()),
             &auth,
             sub.value_of("hidden").unwrap() == "true",
-        )
+            &payloads::WifiOptions {
+                eap,
+                transition_disable: sub.is_present("transition_disable"),
+                public_key: sub.value_of("public_key"),
+            },
+        ))
     } else if let Some(sub) = matches.subcommand_matches(qrterm::MAIL_COMMAND) {
         let encoding = match sub.value_of("encoding") {
             Some("MATMSG") => payloads::MailEncoding::MATMSG,
@@ -153,18 +647,494 @@ fn get_payload(matches: &clap::ArgMatches<'_>) -> String {
             sub.value_of("url").unwrap(),
         )
     } else if let Some(sub) = matches.subcommand_matches(qrterm::BITCOIN_COMMAND) {
-        payloads::bitcoin_string(
+        let network = match sub.value_of("network") {
+            Some("mainnet") => Some(payloads::BitcoinNetwork::Mainnet),
+            Some("testnet") => Some(payloads::BitcoinNetwork::Testnet),
+            Some("signet") => Some(payloads::BitcoinNetwork::Signet),
+            Some("regtest") => Some(payloads::BitcoinNetwork::Regtest),
+            _ => None,
+        };
+        let scheme = match sub.value_of("scheme") {
+            Some("litecoin") => payloads::BitcoinScheme::Litecoin,
+            Some("dogecoin") => payloads::BitcoinScheme::Dogecoin,
+            Some("bitcoincash") => payloads::BitcoinScheme::BitcoinCash,
+            _ => payloads::BitcoinScheme::Bitcoin,
+        };
+        let amount = sub
+            .value_of("amount")
+            .map(|a| or_exit(payloads::parse_satoshis(a, sub.is_present("sat"))));
+
+        // custom parameters come as KEY=VALUE, required ones get the "req-" prefix
+        let mut extra = Vec::new();
+        if let Some(pj) = sub.value_of("payjoin") {
+            extra.push(("pj".to_string(), pj));
+        }
+        for (arg, prefix) in &[("param", ""), ("require", "req-")] {
+            for pair in sub.values_of(arg).into_iter().flatten() {
+                match pair.find('=') {
+                    Some(i) => extra.push((format!("{}{}", prefix, &pair[..i]), &pair[i + 1..])),
+                    None => or_exit(Err(format!("{:?} is not a KEY=VALUE pair", pair))),
+                }
+            }
+        }
+        let extra = extra
+            .iter()
+            .map(|(k, v)| (k.as_str(), *v))
+            .collect::<Vec<_>>();
+
+        or_exit(payloads::bitcoin_string(
+            &scheme,
             sub.value_of("address").unwrap(),
-            sub.value_of("amount")
-                .map(|a| a.parse::<f64>().unwrap_or_default()),
+            network.as_ref(),
+            amount,
             sub.value_of("label"),
             sub.value_of("message"),
-        )
+            &extra,
+        ))
+    } else if let Some(sub) = matches.subcommand_matches(qrterm::GIRO_COMMAND) {
+        or_exit(payloads::giro_string(&payloads::Giro {
+            version: sub.value_of("version").unwrap(),
+            charset: sub.value_of("charset").unwrap(),
+            bic: sub.value_of("bic"),
+            name: sub.value_of("name").unwrap(),
+            iban: sub.value_of("iban").unwrap(),
+            amount: sub.value_of("amount"),
+            purpose: sub.value_of("purpose"),
+            reference: sub.value_of("reference"),
+            text: sub.value_of("text"),
+            information: sub.value_of("information"),
+        }))
+    } else if let Some(sub) = matches.subcommand_matches(qrterm::SWISSQR_COMMAND) {
+        let debtor = sub
+            .value_of("debtor_name")
+            .map(|name| payloads::SwissAddress {
+                name,
+                street: sub.value_of("debtor_street").unwrap_or(""),
+                building: sub.value_of("debtor_building").unwrap_or(""),
+                postcode: sub.value_of("debtor_postcode").unwrap_or(""),
+                town: sub.value_of("debtor_town").unwrap_or(""),
+                country: sub.value_of("debtor_country").unwrap_or(""),
+            });
+        or_exit(payloads::swissqr_string(&payloads::SwissQr {
+            iban: sub.value_of("iban").unwrap(),
+            creditor: payloads::SwissAddress {
+                name: sub.value_of("name").unwrap(),
+                street: sub.value_of("street").unwrap_or(""),
+                building: sub.value_of("building").unwrap_or(""),
+                postcode: sub.value_of("postcode").unwrap(),
+                town: sub.value_of("town").unwrap(),
+                country: sub.value_of("country").unwrap(),
+            },
+            amount: sub.value_of("amount"),
+            currency: sub.value_of("currency").unwrap(),
+            debtor,
+            reference: sub.value_of("reference"),
+            message: sub.value_of("message"),
+            billing: sub.value_of("billing"),
+        }))
+    } else if let Some(sub) = matches.subcommand_matches(qrterm::OTP_COMMAND) {
+        let algorithm = match sub.value_of("algorithm") {
+            Some("SHA256") => payloads::OtpAlgorithm::SHA256,
+            Some("SHA512") => payloads::OtpAlgorithm::SHA512,
+            _ => payloads::OtpAlgorithm::SHA1,
+        };
+        let digits = value_t!(sub, "digits", u32).unwrap_or_else(|e| e.exit());
+        let period = value_t!(sub, "period", u64).unwrap_or_else(|e| e.exit());
+        let counter = value_t!(sub, "counter", u64).unwrap_or_else(|e| e.exit());
+        let kind = match sub.value_of("type") {
+            Some("hotp") => payloads::OtpKind::Hotp { counter },
+            _ => payloads::OtpKind::Totp { period },
+        };
+
+        let secret = match sub.value_of("secret") {
+            Some(s) => s.to_string(),
+            None => {
+                let s = or_exit(payloads::otp_generate_secret(&algorithm));
+                show_generated("secret", &s, matches);
+                s
+            }
+        };
+        let payload = or_exit(payloads::otp_string(
+            &kind,
+            sub.value_of("issuer"),
+            sub.value_of("account").unwrap(),
+            &secret,
+            &algorithm,
+            digits,
+        ));
+
+        // show the code an authenticator should display right now, unless the secret is encrypted
+        if !matches.is_present("encrypt") {
+            let step = match kind {
+                payloads::OtpKind::Totp { period } => {
+                    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+                    now.as_secs() / period
+                }
+                payloads::OtpKind::Hotp { counter } => counter,
+            };
+            println!(
+                "Current code: {}",
+                or_exit(payloads::otp_code(&secret, &algorithm, digits, step))
+            );
+        }
+        payload
+    } else if let Some(sub) = matches.subcommand_matches(qrterm::ETHEREUM_COMMAND) {
+        let chain_id = if sub.is_present("chain_id") {
+            Some(value_t!(sub, "chain_id", u64).unwrap_or_else(|e| e.exit()))
+        } else {
+            None
+        };
+        let value = match sub.value_of("ether") {
+            Some(e) => Some(or_exit(payloads::ether_to_wei(e))),
+            None => sub.value_of("value").map(String::from),
+        };
+        let token = match (sub.value_of("token"), sub.value_of("amount")) {
+            (Some(t), Some(a)) => Some((t, a)),
+            _ => None,
+        };
+        or_exit(payloads::ethereum_string(
+            sub.value_of("address").unwrap(),
+            chain_id,
+            value.as_deref(),
+            token,
+        ))
+    } else if let Some(sub) = matches.subcommand_matches(qrterm::LIGHTNING_COMMAND) {
+        or_exit(payloads::lightning_string(
+            sub.value_of("request").unwrap(),
+            sub.value_of("fallback"),
+        ))
+    } else if let Some(sub) = matches.subcommand_matches(qrterm::DPP_COMMAND) {
+        // lab devices can get a fresh key pair, only the private key file ever holds the secret
+        let generated = sub.value_of("generate_key").map(|path| {
+            let (pem, public) = or_exit(payloads::dpp_generate_key());
+            or_exit(write_private_file(path, pem.as_bytes()));
+            println!("Private key written to: {:?}", path);
+            public
+        });
+        or_exit(payloads::dpp_string(&payloads::Dpp {
+            channels: sub.value_of("channels"),
+            mac: sub.value_of("mac"),
+            info: sub.value_of("info"),
+            version: sub.value_of("version"),
+            host: sub.value_of("host"),
+            key: generated
+                .as_deref()
+                .unwrap_or_else(|| sub.value_of("key").unwrap()),
+        }))
+    } else if let Some(sub) = matches.subcommand_matches(qrterm::MATTER_COMMAND) {
+        let flow = match sub.value_of("flow") {
+            Some("user-intent") => payloads::MatterFlow::UserIntent,
+            Some("custom") => payloads::MatterFlow::Custom,
+            _ => payloads::MatterFlow::Standard,
+        };
+        let discovery = sub
+            .values_of("discovery")
+            .unwrap()
+            .map(|d| match d {
+                "soft-ap" => payloads::MatterDiscovery::SoftAP,
+                "ble" => payloads::MatterDiscovery::BLE,
+                _ => payloads::MatterDiscovery::OnNetwork,
+            })
+            .collect::<Vec<_>>();
+        let matter = payloads::Matter {
+            vendor_id: parse_id(sub.value_of("vendor_id").unwrap()),
+            product_id: parse_id(sub.value_of("product_id").unwrap()),
+            flow,
+            discovery: &discovery,
+            discriminator: value_t!(sub, "discriminator", u16).unwrap_or_else(|e| e.exit()),
+            passcode: value_t!(sub, "passcode", u32).unwrap_or_else(|e| e.exit()),
+        };
+        let payload = or_exit(payloads::matter_string(&matter));
+        println!(
+            "Manual pairing code: {}",
+            or_exit(payloads::matter_manual_code(&matter))
+        );
+        payload
+    } else if let Some(sub) = matches.subcommand_matches(qrterm::ESIM_COMMAND) {
+        or_exit(payloads::esim_string(
+            sub.value_of("smdp").unwrap(),
+            sub.value_of("matching_id").unwrap_or(""),
+            sub.value_of("oid"),
+            sub.is_present("confirmation_code"),
+        ))
+    } else if let Some(sub) = matches.subcommand_matches(qrterm::WIREGUARD_COMMAND) {
+        match sub.value_of("config") {
+            Some(path) => or_exit(payloads::wireguard_config(&read_file(path))),
+            None => {
+                let values = |name| sub.values_of(name).map_or(Vec::new(), |v| v.collect());
+                or_exit(payloads::wireguard_string(&payloads::Wireguard {
+                    private_key: sub.value_of("private_key").unwrap(),
+                    addresses: values("address"),
+                    dns: values("dns"),
+                    peer_public_key: sub.value_of("peer_public_key").unwrap(),
+                    preshared_key: sub.value_of("preshared_key"),
+                    endpoint: sub.value_of("endpoint").unwrap(),
+                    allowed_ips: values("allowed_ips"),
+                    keepalive: sub.value_of("keepalive"),
+                }))
+            }
+        }
+    } else if let Some(sub) = matches.subcommand_matches(qrterm::EMV_COMMAND) {
+        let mut tags = Vec::new();
+        for pair in sub.values_of("tag").into_iter().flatten() {
+            match pair.find('=') {
+                Some(i) => tags.push((&pair[..i], &pair[i + 1..])),
+                None => or_exit(Err(format!("{:?} is not an ID=VALUE pair", pair))),
+            }
+        }
+        or_exit(payloads::emv_string(&payloads::Emv {
+            dynamic: sub.value_of("initiation") == Some("dynamic"),
+            mcc: sub.value_of("mcc").unwrap(),
+            currency: sub.value_of("currency").unwrap(),
+            amount: sub.value_of("amount"),
+            country: sub.value_of("country").unwrap(),
+            name: sub.value_of("name").unwrap(),
+            city: sub.value_of("city").unwrap(),
+            postcode: sub.value_of("postcode"),
+            tags,
+        }))
+    } else if let Some(sub) = matches.subcommand_matches(qrterm::PIX_COMMAND) {
+        or_exit(payloads::pix_string(
+            sub.value_of("key").unwrap(),
+            sub.value_of("name").unwrap(),
+            sub.value_of("city").unwrap(),
+            sub.value_of("txid"),
+            sub.value_of("amount"),
+            sub.value_of("description"),
+        ))
+    } else if let Some((command, sub)) = [qrterm::SPAYD_COMMAND, qrterm::PAYBYSQUARE_COMMAND]
+        .iter()
+        .find_map(|c| matches.subcommand_matches(c).map(|s| (*c, s)))
+    {
+        let payment = payloads::BankPayment {
+            iban: sub.value_of("iban").unwrap(),
+            bic: sub.value_of("bic"),
+            amount: sub.value_of("amount"),
+            currency: sub.value_of("currency").unwrap(),
+            due_date: sub.value_of("due_date"),
+            variable_symbol: sub.value_of("variable_symbol"),
+            constant_symbol: sub.value_of("constant_symbol"),
+            specific_symbol: sub.value_of("specific_symbol"),
+            name: sub.value_of("name"),
+            message: sub.value_of("message"),
+        };
+        if command == qrterm::SPAYD_COMMAND {
+            or_exit(payloads::spayd_string(&payment))
+        } else {
+            or_exit(payloads::paybysquare_string(&payment))
+        }
+    } else if let Some(sub) = matches.subcommand_matches(qrterm::OPENPGP_COMMAND) {
+        let fingerprint = match sub.value_of("key_file") {
+            Some(path) => or_exit(payloads::openpgp_fingerprint(&read_file(path))),
+            None => sub.value_of("fingerprint").unwrap().to_string(),
+        };
+        or_exit(payloads::openpgp_string(&fingerprint))
+    } else if let Some(sub) = matches.subcommand_matches(qrterm::SSH_COMMAND) {
+        let key = read_file(sub.value_of("key_file").unwrap());
+        or_exit(payloads::ssh_string(&key, sub.is_present("full")))
+    } else if let Some(sub) = matches.subcommand_matches(qrterm::MAGNET_COMMAND) {
+        let path = sub.value_of("torrent").unwrap();
+        let torrent = fs::read(path).unwrap_or_else(|e| {
+            eprintln!("Could not read {:?}: {}", path, e);
+            exit(1)
+        });
+        let version = value_t!(sub, "max_version", i16).unwrap_or_else(|e| e.exit());
+        if !(1..=40).contains(&version) {
+            or_exit::<()>(Err("the QR version has to be between 1 and 40".to_string()));
+        }
+        let (link, left_out) = or_exit(payloads::magnet_string(
+            &torrent,
+            version,
+            error_level(matches),
+        ));
+        if left_out > 0 {
+            println!(
+                "Left out {} trackers so the code fits into version {}",
+                left_out, version
+            );
+        }
+        link
+    } else if let Some(sub) = matches.subcommand_matches(qrterm::GS1_COMMAND) {
+        lazy_static! {
+            static ref BRACKETED: Regex = Regex::new(r"\((\d{2,4})\)([^(]+)").unwrap();
+        }
+        // takes AI=VALUE pairs as well as the printed "(01)...(10)..." form
+        let mut ais = Vec::new();
+        for arg in sub.values_of("ai").unwrap() {
+            if arg.starts_with('(') {
+                ais.extend(
+                    BRACKETED
+                        .captures_iter(arg)
+                        .map(|c| (c.get(1).unwrap().as_str(), c.get(2).unwrap().as_str())),
+                );
+            } else {
+                match arg.find('=') {
+                    Some(i) => ais.push((&arg[..i], &arg[i + 1..])),
+                    None => or_exit(Err(format!("{:?} is not an AI=VALUE pair", arg))),
+                }
+            }
+        }
+        or_exit(payloads::gs1_string(
+            &ais,
+            !sub.is_present("element_string"),
+            sub.value_of("domain").unwrap(),
+        ))
     } else {
         String::from(matches.value_of("INPUT").unwrap())
     }
 }
 
+// an encrypted code must not leak its content next to it, decrypting it shows the value again
+fn show_generated(what: &str, value: &str, matches: &clap::ArgMatches<'_>) {
+    if matches.is_present("encrypt") {
+        println!(
+            "Generated a {}, it is only stored in the encrypted code",
+            what
+        );
+    } else {
+        println!("Generated {}: {}", what, value);
+    }
+}
+
+// asks for the passphrase without echoing it, scripts can set QRTERM_PASSPHRASE instead
+fn read_passphrase(confirm: bool) -> Result<String, String> {
+    let prompt = |text: &str| {
+        rpassword::prompt_password(text)
+            .map_err(|e| format!("could not read the passphrase: {}", e))
+    };
+    let passphrase = match std::env::var("QRTERM_PASSPHRASE") {
+        Ok(p) => p,
+        Err(_) => {
+            let p = prompt("Passphrase: ")?;
+            if confirm && !p.is_empty() && prompt("Repeat the passphrase: ")? != p {
+                return Err("the passphrases do not match".to_string());
+            }
+            p
+        }
+    };
+    if passphrase.is_empty() {
+        return Err("the passphrase must not be empty".to_string());
+    }
+    Ok(passphrase)
+}
+
+fn read_file(path: &str) -> String {
+    fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("Could not read {:?}: {}", path, e);
+        exit(1)
+    })
+}
+
+// image files are scanned, text files hold one chunk per line and anything else is a chunk
+fn read_chunks(input: &str) -> Result<Vec<String>, String> {
+    match Path::new(input) {
+        image if is_image(image) => {
+            let data = decode::decode_image_file(input)?;
+            String::from_utf8(data)
+                .map(|c| vec![c])
+                .map_err(|_| format!("{:?} does not hold a backup chunk", input))
+        }
+        _ if Path::new(input).is_file() => fs::read_to_string(input)
+            .map(|s| s.lines().map(str::to_string).collect())
+            .map_err(|e| format!("could not read {:?}: {}", input, e)),
+        _ => Ok(vec![input.to_string()]),
+    }
+}
+
+fn is_image(path: &Path) -> bool {
+    let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+    matches!(
+        extension.as_deref(),
+        Some("png") | Some("jpg") | Some("jpeg") | Some("gif") | Some("bmp")
+    )
+}
+
+// a page that prints every code with its number, so missing sheets are easy to spot
+fn backup_page(file_name: &str, size: usize, images: &[String]) -> String {
+    let name = file_name
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;");
+    let mut page = format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>Backup of {name}</title>
+<style>
+body {{ font-family: sans-serif; }}
+figure {{ display: inline-block; margin: 1em; page-break-inside: avoid; text-align: center; }}
+img {{ width: 8cm; image-rendering: pixelated; }}
+</style>
+</head>
+<body>
+<h1>Backup of {name}</h1>
+<p>{size} bytes in {count} codes. Code 0 is the manifest, every code is needed to restore the file.</p>
+<p>To restore, scan the codes in any order into a text file, one per line, and run
+<code>qr restore FILE</code>. The PNG files next to this page can be passed to
+<code>qr restore</code> directly, photos of the codes need a scanner app.</p>
+",
+        name = name,
+        size = size,
+        count = images.len()
+    );
+    for (i, image) in images.iter().enumerate() {
+        page.push_str(&format!(
+            "<figure><img src=\"{}\"><figcaption>{} &ndash; code {} of {}</figcaption></figure>\n",
+            image,
+            name,
+            i,
+            images.len() - 1
+        ));
+    }
+    page.push_str("</body>\n</html>\n");
+    page
+}
+
+// vendor and product ids are usually written in hex, like 0xFFF1
+fn parse_id(id: &str) -> u16 {
+    let parsed = match id.strip_prefix("0x").or_else(|| id.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => id.parse(),
+    };
+    parsed.unwrap_or_else(|_| {
+        eprintln!("{:?} is not a 16 bit id", id);
+        exit(1)
+    })
+}
+
+// writes secrets so that only the current user can read them
+fn write_private_file(path: &str, content: &[u8]) -> Result<(), String> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(path)
+        .and_then(|mut f| f.write_all(content))
+        .map_err(|e| format!("could not write {:?}: {}", path, e))
+}
+
+// unwraps a validated payload or tells the user what is wrong with the input
+fn or_exit<T>(payload: Result<T, String>) -> T {
+    or_exit_with("Could not create the payload", payload)
+}
+
+// the same for the commands that make or read something else than a payload
+fn or_exit_with<T>(message: &str, result: Result<T, String>) -> T {
+    match result {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("{}: {}", message, e);
+            exit(1);
+        }
+    }
+}
+
 // create the interface for the app with all subcommands, flags and args
 fn build_cli() -> App<'static, 'static> {
     App::new(crate_name!())
@@ -214,11 +1184,186 @@ fn build_cli() -> App<'static, 'static> {
                 .possible_values(&["L", "M", "Q", "H"])
                 .default_value("H"),
         )
+        .arg(
+            Arg::with_name("sign")
+                .global(true)
+                .long("sign")
+                .help("Appends an Ed25519 signature made with the private key in FILE.")
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::with_name("expires")
+                .global(true)
+                .long("expires")
+                .help("Signs an expiry time along, as unix time or a duration like 30d.")
+                .value_name("TIME")
+                .requires("sign"),
+        )
+        .arg(
+            Arg::with_name("encrypt")
+                .global(true)
+                .long("encrypt")
+                .help(
+                    "Encrypts the payload with a passphrase, which is asked for or taken from QRTERM_PASSPHRASE.",
+                )
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("compress")
+                .global(true)
+                .long("compress")
+                .help("Compresses the payload before it is encoded.")
+                .value_name("METHOD")
+                .possible_values(&["zlib", "deflate"]),
+        )
+        .arg(
+            Arg::with_name("encode")
+                .global(true)
+                .long("encode")
+                .help("Encodes the payload as text, base45 and base32 fit the compact alphanumeric mode.")
+                .value_name("ENCODING")
+                .possible_values(&["base45", "base32", "base64url"]),
+        )
         .arg(
             Arg::with_name("INPUT")
                 .help("The input string to use")
                 .required(true),
         )
+        .subcommand(
+            SubCommand::with_name("keygen")
+                .about("Generates an Ed25519 key pair for signing payloads.")
+                .arg(
+                    Arg::with_name("key_file")
+                        .required(true)
+                        .value_name("FILE")
+                        .help("Where to write the private key, the public key goes to FILE.pub"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("verify")
+                .about("Checks the signature of a scanned payload.")
+                .arg(
+                    Arg::with_name("pubkey")
+                        .long("pubkey")
+                        .required(true)
+                        .value_name("KEY")
+                        .help("The public key file, or the raw key in base64"),
+                )
+                .arg(
+                    Arg::with_name("signed")
+                        .value_name("PAYLOAD")
+                        .help("The scanned payload, read from stdin if left out"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("decrypt")
+                .about("Decrypts a scanned payload that was made with --encrypt.")
+                .arg(
+                    Arg::with_name("envelope")
+                        .value_name("PAYLOAD")
+                        .help("The scanned payload, read from stdin if left out"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("backup")
+                .about("Splits a file into numbered codes and a printable page for a paper backup.")
+                .arg(
+                    Arg::with_name("file")
+                        .required(true)
+                        .value_name("FILE")
+                        .help("The file to back up"),
+                )
+                .arg(
+                    Arg::with_name("dir")
+                        .long("dir")
+                        .value_name("DIR")
+                        .help("Where to write the codes and index.html, defaults to FILE-backup"),
+                )
+                .arg(
+                    Arg::with_name("chunk_size")
+                        .long("chunk-size")
+                        .value_name("BYTES")
+                        .default_value("500")
+                        .help("How many compressed bytes go into each code"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("restore")
+                .about("Reassembles a file from the codes of a paper backup.")
+                .arg(
+                    Arg::with_name("chunks")
+                        .multiple(true)
+                        .value_name("INPUT")
+                        .help("The PNG files written by backup, text files with one chunk per line or the chunks themselves, read from stdin if left out"),
+                )
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .value_name("FILE")
+                        .help("Where to write the file, defaults to the name stored in the backup"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("stream")
+                .about("Cycles a file through fountain coded QR-Codes, so a phone can receive it from any large enough set of frames.")
+                .arg(
+                    Arg::with_name("file")
+                        .required(true)
+                        .value_name("FILE")
+                        .help("The file to send"),
+                )
+                .arg(
+                    Arg::with_name("fps")
+                        .long("fps")
+                        .value_name("RATE")
+                        .default_value("5")
+                        .help("How many frames to show per second"),
+                )
+                .arg(
+                    Arg::with_name("block_size")
+                        .long("block-size")
+                        .value_name("BYTES")
+                        .default_value("200")
+                        .help("How many bytes of the file go into each frame"),
+                )
+                .arg(
+                    Arg::with_name("frames")
+                        .long("frames")
+                        .value_name("COUNT")
+                        .help("Stops after COUNT frames, the terminal cycles until interrupted otherwise"),
+                )
+                .arg(
+                    Arg::with_name("dir")
+                        .long("dir")
+                        .value_name("DIR")
+                        .help("Writes the frames as images to DIR instead of showing them"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("unstream")
+                .about("Rebuilds a file from a directory of captured stream frames.")
+                .arg(
+                    Arg::with_name("dir")
+                        .required(true)
+                        .value_name("DIR")
+                        .help("The directory with the frame images written by stream --dir"),
+                )
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .value_name("FILE")
+                        .help("Where to write the file, written to stdout if left out"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("decode")
+                .about("Reverses --encode and --compress on a scanned payload, pass the same options.")
+                .arg(
+                    Arg::with_name("encoded")
+                        .value_name("PAYLOAD")
+                        .help("The scanned payload or an image of the code, read from stdin if left out"),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("completions")
                 .about("Outputs completion files for various shells.")
@@ -240,12 +1385,12 @@ fn build_cli() -> App<'static, 'static> {
         .subcommand(
             SubCommand::with_name(qrterm::WIFI_COMMAND)
                 .about("formats to a wifi access string QR-Code")
-                .arg(Arg::with_name("ssid").required(true))
-                .arg(Arg::with_name("pwd").required(true))
+                .arg(Arg::with_name("ssid").required_unless("from"))
+                .arg(Arg::with_name("pwd").required_unless_one(&["from", "generate_password"]))
                 .arg(
                     Arg::with_name("mode")
                         .value_name("MODE")
-                        .possible_values(&["WEP", "WPA", "nopass"])
+                        .possible_values(&["WEP", "WPA", "SAE", "WPA2-EAP", "nopass"])
                         .default_value("WPA"),
                 )
                 .arg(
@@ -253,6 +1398,74 @@ fn build_cli() -> App<'static, 'static> {
                         .value_name("HIDDEN")
                         .possible_values(&["true", "false"])
                         .default_value("false"),
+                )
+                .arg(
+                    Arg::with_name("eap_method")
+                        .long("eap")
+                        .value_name("METHOD")
+                        .requires("identity")
+                        .help("The EAP method of a WPA2-EAP network, e.g. PEAP or TTLS"),
+                )
+                .arg(
+                    Arg::with_name("identity")
+                        .long("identity")
+                        .value_name("IDENTITY")
+                        .requires("eap_method"),
+                )
+                .arg(
+                    Arg::with_name("anonymous_identity")
+                        .long("anonymous-identity")
+                        .value_name("IDENTITY")
+                        .requires("eap_method"),
+                )
+                .arg(
+                    Arg::with_name("phase2")
+                        .long("phase2")
+                        .value_name("METHOD")
+                        .requires("eap_method")
+                        .help("The phase 2 authentication, e.g. MSCHAPV2"),
+                )
+                .arg(
+                    Arg::with_name("transition_disable")
+                        .long("transition-disable")
+                        .help("Tells devices to stop falling back to older security modes"),
+                )
+                .arg(
+                    Arg::with_name("public_key")
+                        .long("public-key")
+                        .value_name("KEY")
+                        .help("The base64 SAE-PK public key of a SAE network"),
+                )
+                .arg(
+                    Arg::with_name("generate_password")
+                        .long("generate-password")
+                        .conflicts_with("from")
+                        .help("Generates a random passphrase and prints it once, pass - as PWD to still give MODE and HIDDEN"),
+                )
+                .arg(
+                    Arg::with_name("length")
+                        .long("length")
+                        .value_name("N")
+                        .requires("generate_password")
+                        .help("The length of the generated passphrase, 8 to 63 [default: 20]"),
+                )
+                .arg(
+                    Arg::with_name("charset")
+                        .long("charset")
+                        .value_name("CHARSET")
+                        .possible_values(&["alphanumeric", "readable", "symbols"])
+                        .requires("generate_password")
+                        .help("The characters of the generated passphrase [default: alphanumeric]"),
+                )
+                .arg(
+                    Arg::with_name("from")
+                        .long("from")
+                        .value_name("FILE")
+                        .conflicts_with("pwd")
+                        .help(
+                            "Reads the network from a NetworkManager .nmconnection keyfile or a
+            wpa_supplicant.conf, the ssid picks one of several networks.",
+                        ),
                 ),
         )
         .subcommand(
@@ -333,8 +1546,706 @@ fn build_cli() -> App<'static, 'static> {
             SubCommand::with_name(qrterm::BITCOIN_COMMAND)
                 .about("outputs a bitcoin adress/transaction")
                 .arg(Arg::with_name("address").required(true))
-                .arg(Arg::with_name("amount"))
+                .arg(
+                    Arg::with_name("amount")
+                        .help("The exact amount as a decimal, e.g. 0.0015"),
+                )
                 .arg(Arg::with_name("label"))
-                .arg(Arg::with_name("message")),
+                .arg(Arg::with_name("message"))
+                .arg(
+                    Arg::with_name("network")
+                        .long("network")
+                        .value_name("NETWORK")
+                        .possible_values(&["mainnet", "testnet", "signet", "regtest"])
+                        .help("Rejects addresses that do not belong to this network"),
+                )
+                .arg(
+                    Arg::with_name("scheme")
+                        .long("scheme")
+                        .value_name("SCHEME")
+                        .possible_values(&["bitcoin", "litecoin", "dogecoin", "bitcoincash"])
+                        .default_value("bitcoin"),
+                )
+                .arg(
+                    Arg::with_name("sat")
+                        .long("sat")
+                        .help("Reads the amount as an integer of satoshis (or the coin's base unit)"),
+                )
+                .arg(
+                    Arg::with_name("payjoin")
+                        .long("payjoin")
+                        .value_name("URL")
+                        .help("Adds a BIP-78 payjoin endpoint"),
+                )
+                .arg(
+                    Arg::with_name("param")
+                        .long("param")
+                        .value_name("KEY=VALUE")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("Adds a custom optional parameter"),
+                )
+                .arg(
+                    Arg::with_name("require")
+                        .long("require")
+                        .value_name("KEY=VALUE")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("Adds a custom req- parameter that wallets must understand"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(qrterm::GIRO_COMMAND)
+                .about("formats to a GiroCode (EPC069-12) SEPA credit transfer QR-Code")
+                .arg(Arg::with_name("name").required(true).value_name("NAME"))
+                .arg(Arg::with_name("iban").required(true).value_name("IBAN"))
+                .arg(
+                    Arg::with_name("amount")
+                        .value_name("AMOUNT")
+                        .help("The amount in EUR, e.g. 12.50"),
+                )
+                .arg(
+                    Arg::with_name("bic")
+                        .long("bic")
+                        .value_name("BIC")
+                        .help("The BIC of the beneficiary bank, required for version 001"),
+                )
+                .arg(
+                    Arg::with_name("purpose")
+                        .long("purpose")
+                        .value_name("CODE")
+                        .help("A four letter purpose code"),
+                )
+                .arg(
+                    Arg::with_name("reference")
+                        .long("reference")
+                        .value_name("REFERENCE")
+                        .conflicts_with("text")
+                        .help("A structured remittance reference"),
+                )
+                .arg(
+                    Arg::with_name("text")
+                        .long("text")
+                        .value_name("TEXT")
+                        .help("An unstructured remittance text"),
+                )
+                .arg(
+                    Arg::with_name("information")
+                        .long("information")
+                        .value_name("TEXT")
+                        .help("A note from the beneficiary to the originator"),
+                )
+                .arg(
+                    Arg::with_name("version")
+                        .long("version")
+                        .value_name("VERSION")
+                        .possible_values(&["001", "002"])
+                        .default_value("002"),
+                )
+                .arg(
+                    Arg::with_name("charset")
+                        .long("charset")
+                        .value_name("CHARSET")
+                        .possible_values(&["1", "2", "3", "4", "5", "6", "7", "8"])
+                        .default_value("1")
+                        .help("The character set, 1 is UTF-8 and 2-8 are ISO 8859 variants"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(qrterm::ETHEREUM_COMMAND)
+                .about("formats to an ethereum (EIP-681) payment request QR-Code")
+                .arg(
+                    Arg::with_name("address")
+                        .required(true)
+                        .value_name("ADDRESS")
+                        .help("The receiving address, checked against its EIP-55 checksum"),
+                )
+                .arg(
+                    Arg::with_name("chain_id")
+                        .long("chain-id")
+                        .value_name("ID")
+                        .help("The chain id of the network, e.g. 1 for mainnet"),
+                )
+                .arg(
+                    Arg::with_name("value")
+                        .long("value")
+                        .value_name("WEI")
+                        .conflicts_with_all(&["ether", "token"])
+                        .help("The amount of ether to send as an integer in wei"),
+                )
+                .arg(
+                    Arg::with_name("ether")
+                        .long("ether")
+                        .value_name("ETHER")
+                        .conflicts_with("token")
+                        .help("The amount of ether to send as a decimal, e.g. 0.25"),
+                )
+                .arg(
+                    Arg::with_name("token")
+                        .long("token")
+                        .value_name("CONTRACT")
+                        .requires("amount")
+                        .help("Requests an ERC-20 transfer from this token contract instead"),
+                )
+                .arg(
+                    Arg::with_name("amount")
+                        .long("amount")
+                        .value_name("UNITS")
+                        .requires("token")
+                        .help("The token amount as an integer in the smallest token unit"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(qrterm::LIGHTNING_COMMAND)
+                .about("formats to a lightning invoice or LNURL QR-Code")
+                .arg(
+                    Arg::with_name("request")
+                        .required(true)
+                        .value_name("REQUEST")
+                        .help("A BOLT11 invoice, a LNURL or a https url to encode as LNURL"),
+                )
+                .arg(
+                    Arg::with_name("fallback")
+                        .long("fallback")
+                        .value_name("ADDRESS")
+                        .help("Creates a BIP-21 unified code with this on-chain fallback address"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(qrterm::GS1_COMMAND)
+                .about("formats to a GS1 Digital Link or element string QR-Code")
+                .arg(
+                    Arg::with_name("ai")
+                        .required(true)
+                        .multiple(true)
+                        .value_name("AI=VALUE")
+                        .help("Application Identifiers like 01=09506000134352 or (17)251231"),
+                )
+                .arg(
+                    Arg::with_name("element_string")
+                        .long("element-string")
+                        .conflicts_with("domain")
+                        .help("Encodes the classic element string with FNC1 instead of a URI"),
+                )
+                .arg(
+                    Arg::with_name("domain")
+                        .long("domain")
+                        .value_name("URL")
+                        .default_value("https://id.gs1.org")
+                        .help("The resolver the Digital Link points to"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(qrterm::MAGNET_COMMAND)
+                .about("formats to a BitTorrent magnet link QR-Code")
+                .arg(
+                    Arg::with_name("torrent")
+                        .required(true)
+                        .value_name("FILE")
+                        .help("The .torrent file to link to"),
+                )
+                .arg(
+                    Arg::with_name("max_version")
+                        .long("max-version")
+                        .value_name("VERSION")
+                        .default_value("40")
+                        .help("Leaves out trackers until the code fits into this QR version"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(qrterm::OPENPGP_COMMAND)
+                .about("formats to an OpenPGP fingerprint (OPENPGP4FPR) QR-Code")
+                .arg(
+                    Arg::with_name("fingerprint")
+                        .value_name("FINGERPRINT")
+                        .required_unless("key_file")
+                        .help("The 40 hex digit v4 fingerprint"),
+                )
+                .arg(
+                    Arg::with_name("key_file")
+                        .long("key-file")
+                        .value_name("FILE")
+                        .conflicts_with("fingerprint")
+                        .help("An ASCII-armored public key to take the fingerprint from"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(qrterm::SSH_COMMAND)
+                .about("formats to a QR-Code with the fingerprint of an OpenSSH public key")
+                .arg(
+                    Arg::with_name("key_file")
+                        .required(true)
+                        .value_name("FILE")
+                        .help("The public key, e.g. /etc/ssh/ssh_host_ed25519_key.pub"),
+                )
+                .arg(
+                    Arg::with_name("full")
+                        .long("full")
+                        .help("Encodes the whole key instead of its SHA256 fingerprint"),
+                ),
+        )
+        .subcommand(bank_payment_command(
+            qrterm::SPAYD_COMMAND,
+            "formats to a czech SPAYD (QR Platba) payment QR-Code",
+            "CZK",
+        ))
+        .subcommand(bank_payment_command(
+            qrterm::PAYBYSQUARE_COMMAND,
+            "formats to a slovak PAY by square payment QR-Code",
+            "EUR",
+        ))
+        .subcommand(
+            SubCommand::with_name(qrterm::EMV_COMMAND)
+                .about("formats to an EMVCo merchant-presented payment QR-Code")
+                .arg(Arg::with_name("name").required(true).value_name("NAME"))
+                .arg(Arg::with_name("city").required(true).value_name("CITY"))
+                .arg(
+                    Arg::with_name("country")
+                        .long("country")
+                        .value_name("CODE")
+                        .required(true)
+                        .help("The two letter country code of the merchant"),
+                )
+                .arg(
+                    Arg::with_name("currency")
+                        .long("currency")
+                        .value_name("CODE")
+                        .required(true)
+                        .help("The numeric ISO 4217 currency, e.g. 702 for SGD"),
+                )
+                .arg(
+                    Arg::with_name("mcc")
+                        .long("mcc")
+                        .value_name("MCC")
+                        .default_value("0000")
+                        .help("The merchant category code"),
+                )
+                .arg(Arg::with_name("amount").long("amount").value_name("AMOUNT"))
+                .arg(
+                    Arg::with_name("initiation")
+                        .long("initiation")
+                        .value_name("METHOD")
+                        .possible_values(&["static", "dynamic"])
+                        .default_value("static")
+                        .help("Whether the code is reused or made for a single payment"),
+                )
+                .arg(Arg::with_name("postcode").long("postcode").value_name("POSTCODE"))
+                .arg(
+                    Arg::with_name("tag")
+                        .long("tag")
+                        .value_name("ID=VALUE")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("Adds a raw field, use ID.SUB=VALUE inside templates like 26 or 62"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(qrterm::PIX_COMMAND)
+                .about("formats to a brazilian PIX payment QR-Code")
+                .arg(
+                    Arg::with_name("key")
+                        .required(true)
+                        .value_name("KEY")
+                        .help("The PIX key: a CPF/CNPJ, phone number, email or random key"),
+                )
+                .arg(Arg::with_name("name").required(true).value_name("NAME"))
+                .arg(Arg::with_name("city").required(true).value_name("CITY"))
+                .arg(
+                    Arg::with_name("txid")
+                        .long("txid")
+                        .value_name("TXID")
+                        .help("The transaction id to reconcile the payment with"),
+                )
+                .arg(Arg::with_name("amount").long("amount").value_name("AMOUNT"))
+                .arg(
+                    Arg::with_name("description")
+                        .long("description")
+                        .value_name("TEXT"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(qrterm::WIREGUARD_COMMAND)
+                .about("formats to a WireGuard tunnel configuration QR-Code")
+                .arg(
+                    Arg::with_name("config")
+                        .value_name("CONFIG")
+                        .required_unless_one(&["private_key"])
+                        .help("A wg-quick .conf file to read the tunnel from"),
+                )
+                .arg(
+                    Arg::with_name("private_key")
+                        .long("private-key")
+                        .value_name("KEY")
+                        .conflicts_with("config")
+                        .requires_all(&["address", "peer_public_key", "endpoint"])
+                        .help("The private key of the interface"),
+                )
+                .arg(
+                    Arg::with_name("address")
+                        .long("address")
+                        .value_name("CIDR")
+                        .multiple(true)
+                        .use_delimiter(true)
+                        .help("The addresses of the interface"),
+                )
+                .arg(
+                    Arg::with_name("dns")
+                        .long("dns")
+                        .value_name("SERVER")
+                        .multiple(true)
+                        .use_delimiter(true)
+                        .help("The DNS servers to use inside the tunnel"),
+                )
+                .arg(
+                    Arg::with_name("peer_public_key")
+                        .long("peer-public-key")
+                        .value_name("KEY")
+                        .help("The public key of the peer"),
+                )
+                .arg(
+                    Arg::with_name("preshared_key")
+                        .long("preshared-key")
+                        .value_name("KEY")
+                        .help("An additional symmetric key shared with the peer"),
+                )
+                .arg(
+                    Arg::with_name("endpoint")
+                        .long("endpoint")
+                        .value_name("HOST:PORT")
+                        .help("Where the peer can be reached"),
+                )
+                .arg(
+                    Arg::with_name("allowed_ips")
+                        .long("allowed-ips")
+                        .value_name("CIDR")
+                        .multiple(true)
+                        .use_delimiter(true)
+                        .default_value("0.0.0.0/0,::/0")
+                        .help("The addresses routed through the tunnel"),
+                )
+                .arg(
+                    Arg::with_name("keepalive")
+                        .long("keepalive")
+                        .value_name("SECONDS")
+                        .help("Sends a keepalive packet every SECONDS"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(qrterm::ESIM_COMMAND)
+                .about("formats to an eSIM activation code (LPA) QR-Code")
+                .arg(
+                    Arg::with_name("smdp")
+                        .required(true)
+                        .value_name("SMDP")
+                        .help("The address of the SM-DP+ server"),
+                )
+                .arg(
+                    Arg::with_name("matching_id")
+                        .value_name("MATCHING_ID")
+                        .help("The activation token handed out by the operator"),
+                )
+                .arg(
+                    Arg::with_name("oid")
+                        .long("oid")
+                        .value_name("OID")
+                        .help("The object identifier of the SM-DP+ server"),
+                )
+                .arg(
+                    Arg::with_name("confirmation_code")
+                        .long("confirmation-code")
+                        .help("Marks that the user has to enter a confirmation code"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(qrterm::MATTER_COMMAND)
+                .about("formats to a Matter smart-home onboarding QR-Code")
+                .arg(
+                    Arg::with_name("passcode")
+                        .required(true)
+                        .value_name("PASSCODE")
+                        .help("The 27 bit setup passcode"),
+                )
+                .arg(
+                    Arg::with_name("discriminator")
+                        .required(true)
+                        .value_name("DISCRIMINATOR")
+                        .help("The 12 bit discriminator"),
+                )
+                .arg(
+                    Arg::with_name("vendor_id")
+                        .long("vendor-id")
+                        .value_name("ID")
+                        .default_value("0")
+                        .help("The vendor id, decimal or 0x prefixed hex"),
+                )
+                .arg(
+                    Arg::with_name("product_id")
+                        .long("product-id")
+                        .value_name("ID")
+                        .default_value("0")
+                        .help("The product id, decimal or 0x prefixed hex"),
+                )
+                .arg(
+                    Arg::with_name("flow")
+                        .long("flow")
+                        .value_name("FLOW")
+                        .possible_values(&["standard", "user-intent", "custom"])
+                        .default_value("standard"),
+                )
+                .arg(
+                    Arg::with_name("discovery")
+                        .long("discovery")
+                        .value_name("CAPABILITY")
+                        .multiple(true)
+                        .use_delimiter(true)
+                        .possible_values(&["soft-ap", "ble", "on-network"])
+                        .default_value("ble")
+                        .help("How the device can be found, several can be given"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(qrterm::DPP_COMMAND)
+                .about("formats to a Wi-Fi Easy Connect (DPP) bootstrapping QR-Code")
+                .arg(
+                    Arg::with_name("key")
+                        .value_name("KEY")
+                        .required_unless("generate_key")
+                        .help("The base64 DER encoded public key of the device"),
+                )
+                .arg(
+                    Arg::with_name("generate_key")
+                        .long("generate-key")
+                        .value_name("FILE")
+                        .conflicts_with("key")
+                        .help("Generates a P-256 key pair and writes the private key to FILE"),
+                )
+                .arg(
+                    Arg::with_name("channels")
+                        .long("channels")
+                        .value_name("LIST")
+                        .help("The class/channel pairs to listen on, e.g. 81/1,115/36"),
+                )
+                .arg(
+                    Arg::with_name("mac")
+                        .long("mac")
+                        .value_name("MAC")
+                        .help("The MAC address of the device"),
+                )
+                .arg(
+                    Arg::with_name("info")
+                        .long("info")
+                        .value_name("INFO")
+                        .help("Free-form device information, e.g. a serial number"),
+                )
+                .arg(
+                    Arg::with_name("version")
+                        .long("version")
+                        .value_name("VERSION")
+                        .help("The DPP protocol version, e.g. 2"),
+                )
+                .arg(
+                    Arg::with_name("host")
+                        .long("host")
+                        .value_name("HOST")
+                        .help("The host name or address of the device"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(qrterm::OTP_COMMAND)
+                .about("formats to a TOTP/HOTP authenticator provisioning QR-Code")
+                .arg(Arg::with_name("account").required(true).value_name("ACCOUNT"))
+                .arg(
+                    Arg::with_name("secret")
+                        .value_name("SECRET")
+                        .required_unless("generate")
+                        .help("The base32 encoded shared secret"),
+                )
+                .arg(
+                    Arg::with_name("generate")
+                        .long("generate")
+                        .conflicts_with("secret")
+                        .help("Generates a random secret and prints it once"),
+                )
+                .arg(Arg::with_name("issuer").long("issuer").value_name("ISSUER"))
+                .arg(
+                    Arg::with_name("type")
+                        .long("type")
+                        .value_name("TYPE")
+                        .possible_values(&["totp", "hotp"])
+                        .default_value("totp"),
+                )
+                .arg(
+                    Arg::with_name("algorithm")
+                        .long("algorithm")
+                        .value_name("ALGORITHM")
+                        .possible_values(&["SHA1", "SHA256", "SHA512"])
+                        .default_value("SHA1"),
+                )
+                .arg(
+                    Arg::with_name("digits")
+                        .long("digits")
+                        .value_name("DIGITS")
+                        .possible_values(&["6", "7", "8"])
+                        .default_value("6"),
+                )
+                .arg(
+                    Arg::with_name("period")
+                        .long("period")
+                        .value_name("SECONDS")
+                        .default_value("30")
+                        .help("The time step of a TOTP code"),
+                )
+                .arg(
+                    Arg::with_name("counter")
+                        .long("counter")
+                        .value_name("COUNTER")
+                        .default_value("0")
+                        .help("The initial counter of a HOTP code"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(qrterm::SWISSQR_COMMAND)
+                .about("formats to a Swiss QR-bill payment part QR-Code")
+                .arg(
+                    Arg::with_name("iban")
+                        .required(true)
+                        .value_name("IBAN")
+                        .help("The IBAN or QR-IBAN of the creditor"),
+                )
+                .arg(Arg::with_name("name").required(true).value_name("NAME"))
+                .arg(Arg::with_name("postcode").required(true).value_name("POSTCODE"))
+                .arg(Arg::with_name("town").required(true).value_name("TOWN"))
+                .arg(
+                    Arg::with_name("country")
+                        .value_name("COUNTRY")
+                        .default_value("CH"),
+                )
+                .arg(Arg::with_name("street").long("street").value_name("STREET"))
+                .arg(
+                    Arg::with_name("building")
+                        .long("building")
+                        .value_name("NUMBER"),
+                )
+                .arg(
+                    Arg::with_name("amount")
+                        .long("amount")
+                        .value_name("AMOUNT")
+                        .help("The amount to pay, leave out to let the debtor fill it in"),
+                )
+                .arg(
+                    Arg::with_name("currency")
+                        .long("currency")
+                        .value_name("CURRENCY")
+                        .possible_values(&["CHF", "EUR"])
+                        .default_value("CHF"),
+                )
+                .arg(
+                    Arg::with_name("reference")
+                        .long("reference")
+                        .value_name("REFERENCE")
+                        .help("A QR reference for QR-IBANs, otherwise an RF creditor reference"),
+                )
+                .arg(
+                    Arg::with_name("message")
+                        .long("message")
+                        .value_name("MESSAGE")
+                        .help("An unstructured message for the debtor"),
+                )
+                .arg(
+                    Arg::with_name("billing")
+                        .long("billing")
+                        .value_name("INFO")
+                        .help("Structured billing information, e.g. in the swico format"),
+                )
+                .arg(
+                    Arg::with_name("debtor_name")
+                        .long("debtor-name")
+                        .value_name("NAME")
+                        .requires_all(&["debtor_postcode", "debtor_town", "debtor_country"]),
+                )
+                .arg(
+                    Arg::with_name("debtor_street")
+                        .long("debtor-street")
+                        .value_name("STREET")
+                        .requires("debtor_name"),
+                )
+                .arg(
+                    Arg::with_name("debtor_building")
+                        .long("debtor-building")
+                        .value_name("NUMBER")
+                        .requires("debtor_name"),
+                )
+                .arg(
+                    Arg::with_name("debtor_postcode")
+                        .long("debtor-postcode")
+                        .value_name("POSTCODE")
+                        .requires("debtor_name"),
+                )
+                .arg(
+                    Arg::with_name("debtor_town")
+                        .long("debtor-town")
+                        .value_name("TOWN")
+                        .requires("debtor_name"),
+                )
+                .arg(
+                    Arg::with_name("debtor_country")
+                        .long("debtor-country")
+                        .value_name("COUNTRY")
+                        .requires("debtor_name"),
+                ),
+        )
+}
+
+// spayd and paybysquare take the same arguments
+fn bank_payment_command(
+    name: &'static str,
+    about: &'static str,
+    currency: &'static str,
+) -> App<'static, 'static> {
+    SubCommand::with_name(name)
+        .about(about)
+        .arg(Arg::with_name("iban").required(true).value_name("IBAN"))
+        .arg(Arg::with_name("bic").long("bic").value_name("BIC"))
+        .arg(Arg::with_name("amount").long("amount").value_name("AMOUNT"))
+        .arg(
+            Arg::with_name("currency")
+                .long("currency")
+                .value_name("CODE")
+                .default_value(currency),
+        )
+        .arg(
+            Arg::with_name("due_date")
+                .long("due-date")
+                .value_name("DATE")
+                .help("The date the payment is due, like 2024-01-31"),
+        )
+        .arg(
+            Arg::with_name("variable_symbol")
+                .long("vs")
+                .value_name("SYMBOL")
+                .help("The variable symbol identifying the payment"),
+        )
+        .arg(
+            Arg::with_name("constant_symbol")
+                .long("ks")
+                .value_name("SYMBOL")
+                .help("The constant symbol describing the kind of payment"),
+        )
+        .arg(
+            Arg::with_name("specific_symbol")
+                .long("ss")
+                .value_name("SYMBOL")
+                .help("The specific symbol, e.g. a customer number"),
+        )
+        .arg(
+            Arg::with_name("name")
+                .long("name")
+                .value_name("NAME")
+                .help("The name of the beneficiary"),
+        )
+        .arg(
+            Arg::with_name("message")
+                .long("message")
+                .value_name("MESSAGE"),
         )
 }
@@ -1,33 +1,208 @@
+use crate::encoding::{
+    base32_decode, base32_encode, base32hex_encode, base38_encode, base58_decode, base64_decode,
+    base64_encode, bech32_decode, bech32_encode, bencode_decode, convert_bits, Bech32Variant,
+    Bencode, BASE58_ALPHABET, BECH32_ALPHABET,
+};
+use hmac::{Hmac, Mac};
+use qrcode::{EcLevel, QrCode, Version};
 use regex::Regex;
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+use sha3::{Digest, Keccak256};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
 use urlparse::quote;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 #[allow(dead_code)]
 #[allow(non_camel_case_types)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum Authentication {
     WEP,
     WPA,
+    SAE,
+    WPA2_EAP,
     nopass,
 }
 
+impl Authentication {
+    fn as_str(&self) -> &'static str {
+        match *self {
+            Authentication::WEP => "WEP",
+            Authentication::WPA => "WPA",
+            Authentication::SAE => "SAE",
+            Authentication::WPA2_EAP => "WPA2-EAP",
+            Authentication::nopass => "nopass",
+        }
+    }
+}
+
+/// The 802.1X settings of a WPA2-Enterprise network.
+#[derive(Debug, Default)]
+pub struct WifiEap<'a> {
+    pub method: &'a str,
+    pub identity: &'a str,
+    pub anonymous_identity: Option<&'a str>,
+    pub phase2: Option<&'a str>,
+}
+
+/// The optional fields a Wi-Fi code can carry next to SSID and password.
+#[derive(Debug, Default)]
+pub struct WifiOptions<'a> {
+    pub eap: Option<WifiEap<'a>>,
+    pub transition_disable: bool,
+    pub public_key: Option<&'a str>,
+}
+
+// builds the ZXing/Wi-Fi Alliance format, e.g. WIFI:T:WPA;S:ssid;P:password;H:true;;
 #[allow(dead_code)]
-pub fn wifi_string(ssid: &str, password: &str, mode: &Authentication, is_hidden: bool) -> String {
-    let ssid_n = escape_input(ssid, false);
-    let sn = "\"".to_string() + &ssid_n + "\"";
-    let ssid_n = if is_hexstyle(&ssid_n) { sn } else { ssid_n };
-    let password_n = escape_input(password, false);
-    let pn = "\"".to_string() + &password_n + "\"";
-    let password_n = if is_hexstyle(&password_n) {
-        pn
+pub fn wifi_string(
+    ssid: &str,
+    password: &str,
+    mode: &Authentication,
+    is_hidden: bool,
+    options: &WifiOptions,
+) -> Result<String, String> {
+    if ssid.is_empty() || ssid.len() > 32 {
+        return Err("the SSID must be between 1 and 32 bytes long".to_string());
+    }
+    if *mode == Authentication::nopass && !password.is_empty() {
+        return Err("open networks can not have a password".to_string());
+    }
+
+    // scanners configure an all-hex SSID as raw bytes unless it is quoted
+    let ssid_n = escape_wifi(ssid);
+    let ssid_n = if is_hexstyle(ssid) {
+        format!("\"{}\"", ssid_n)
     } else {
-        password_n
+        ssid_n
     };
-    let hidden = if is_hidden { "H:true" } else { "" };
 
-    return format!(
-        "WIFI:T:{:?};S:{};P:{};{};",
-        mode, ssid_n, password_n, hidden
-    );
+    let mut fields = vec![("T", mode.as_str().to_string())];
+    if options.transition_disable {
+        if *mode != Authentication::WPA && *mode != Authentication::SAE {
+            return Err("transition disable only applies to WPA and SAE networks".to_string());
+        }
+        fields.push(("R", "1".to_string()));
+    }
+    fields.push(("S", ssid_n));
+    // passwords are never quoted: an all-hex password is only read as a raw key at the
+    // lengths (64 for WPA, 10/26/58 for WEP) that can not be a passphrase anyway
+    if *mode != Authentication::nopass {
+        fields.push(("P", escape_wifi(password)));
+    }
+
+    match (mode, &options.eap) {
+        (Authentication::WPA2_EAP, Some(eap)) => {
+            lazy_static! {
+                static ref METHODS: Vec<&'static str> =
+                    vec!["PEAP", "TLS", "TTLS", "PWD", "SIM", "AKA", "AKA'"];
+                static ref PHASE2: Vec<&'static str> =
+                    vec!["NONE", "PAP", "MSCHAP", "MSCHAPV2", "GTC", "SIM", "AKA", "AKA'"];
+            }
+            let method = eap.method.to_uppercase();
+            if !METHODS.contains(&method.as_str()) {
+                return Err(format!("{:?} is not a known EAP method", eap.method));
+            }
+            if eap.identity.is_empty() {
+                return Err("WPA2-EAP networks need an identity".to_string());
+            }
+            fields.push(("E", escape_wifi(&method)));
+            if let Some(a) = eap.anonymous_identity {
+                // readers disagree on the key of the anonymous identity, so write both
+                fields.push(("A", escape_wifi(a)));
+                fields.push(("AI", escape_wifi(a)));
+            }
+            fields.push(("I", escape_wifi(eap.identity)));
+            if let Some(p) = eap.phase2 {
+                let p = p.to_uppercase();
+                if method == "TLS" {
+                    return Err("EAP-TLS has no phase 2 authentication".to_string());
+                }
+                if !PHASE2.contains(&p.as_str()) {
+                    return Err(format!("{:?} is not a known phase 2 method", p));
+                }
+                fields.push(("PH2", escape_wifi(&p)));
+            }
+        }
+        (Authentication::WPA2_EAP, None) => {
+            return Err("WPA2-EAP networks need an EAP method and identity".to_string());
+        }
+        (_, Some(_)) => {
+            return Err("EAP settings are only used by WPA2-EAP networks".to_string());
+        }
+        (_, None) => {}
+    }
+
+    if let Some(k) = options.public_key {
+        if *mode != Authentication::SAE {
+            return Err("a SAE-PK public key needs the SAE mode".to_string());
+        }
+        lazy_static! {
+            static ref BASE64: Regex = Regex::new(r"\A[A-Za-z0-9+/]+={0,2}\z").unwrap();
+        }
+        if !BASE64.is_match(k) {
+            return Err("the public key has to be base64 encoded".to_string());
+        }
+        fields.push(("K", k.to_string()));
+    }
+    if is_hidden {
+        fields.push(("H", "true".to_string()));
+    }
+
+    let joined = fields
+        .iter()
+        .map(|(key, value)| format!("{}:{};", key, value))
+        .collect::<String>();
+    Ok(format!("WIFI:{};", joined))
+}
+
+#[derive(Debug)]
+#[allow(dead_code)]
+pub enum PasswordCharset {
+    Alphanumeric,
+    Readable,
+    Symbols,
+}
+
+impl PasswordCharset {
+    // none of these needs escaping in a wifi code
+    fn characters(&self) -> Vec<u8> {
+        match *self {
+            PasswordCharset::Alphanumeric => (b'0'..=b'9')
+                .chain(b'A'..=b'Z')
+                .chain(b'a'..=b'z')
+                .collect(),
+            // leaves out characters that are easily confused when read from a screen
+            PasswordCharset::Readable => {
+                b"23456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnpqrstuvwxyz".to_vec()
+            }
+            PasswordCharset::Symbols => (b'!'..=b'~').filter(|c| !b"\\;,:\"".contains(c)).collect(),
+        }
+    }
+}
+
+// creates a WPA passphrase from the operating system's secure random number generator
+#[allow(dead_code)]
+pub fn wifi_generate_password(length: usize, charset: &PasswordCharset) -> Result<String, String> {
+    if !(8..=63).contains(&length) {
+        return Err("WPA passphrases are 8 to 63 characters long".to_string());
+    }
+
+    let characters = charset.characters();
+    // reject bytes above the largest multiple of the charset size to avoid a modulo bias
+    let limit = 256 - 256 % characters.len();
+    let mut password = String::with_capacity(length);
+    let mut buffer = [0u8; 64];
+    while password.len() < length {
+        getrandom::getrandom(&mut buffer).map_err(|e| e.to_string())?;
+        for &b in buffer.iter().filter(|&&b| (b as usize) < limit) {
+            if password.len() < length {
+                password.push(characters[b as usize % characters.len()] as char);
+            }
+        }
+    }
+    Ok(password)
 }
 
 #[derive(Debug)]
@@ -147,13 +322,69 @@ pub fn phone_string(number: &str) -> String {
     format!("tel:{}", number)
 }
 
+#[derive(Debug, PartialEq)]
+#[allow(dead_code)]
+pub enum BitcoinNetwork {
+    Mainnet,
+    Testnet,
+    Signet,
+    Regtest,
+}
+
+#[derive(Debug)]
+#[allow(dead_code)]
+pub enum BitcoinScheme {
+    Bitcoin,
+    Litecoin,
+    Dogecoin,
+    BitcoinCash,
+}
+
+impl BitcoinScheme {
+    fn uri_scheme(&self) -> &'static str {
+        match *self {
+            BitcoinScheme::Bitcoin => "bitcoin",
+            BitcoinScheme::Litecoin => "litecoin",
+            BitcoinScheme::Dogecoin => "dogecoin",
+            BitcoinScheme::BitcoinCash => "bitcoincash",
+        }
+    }
+
+    // the most base units that can ever exist, dogecoin has no cap
+    fn max_supply(&self) -> u64 {
+        match *self {
+            BitcoinScheme::Bitcoin | BitcoinScheme::BitcoinCash => 21_000_000 * 100_000_000,
+            BitcoinScheme::Litecoin => 84_000_000 * 100_000_000,
+            BitcoinScheme::Dogecoin => u64::MAX,
+        }
+    }
+}
+
 #[allow(dead_code)]
 pub fn bitcoin_string(
+    scheme: &BitcoinScheme,
     address: &str,
-    amount: Option<f64>,
+    network: Option<&BitcoinNetwork>,
+    amount: Option<u64>,
     label: Option<&str>,
     message: Option<&str>,
-) -> String {
+    extra: &[(&str, &str)],
+) -> Result<String, String> {
+    let (address, networks) = coin_address_networks(scheme, address)?;
+    if let Some(n) = network {
+        if !networks.contains(n) {
+            let names = networks
+                .iter()
+                .map(|n| format!("{:?}", n))
+                .collect::<Vec<_>>()
+                .join(" or ");
+            return Err(format!(
+                "{:?} is a {} address, not a {:?} one",
+                address, names, n
+            ));
+        }
+    }
+
     // used for our little filter/map magic
     struct KeyValuePair {
         key: String,
@@ -169,23 +400,54 @@ pub fn bitcoin_string(
         None => "".to_string(),
     };
     let a = match amount {
-        Some(x) => format!("{0:.8}", x),
+        Some(0) => return Err("the amount must be larger than zero".to_string()),
+        Some(x) if x > scheme.max_supply() => {
+            return Err(format!(
+                "{} is more than will ever exist",
+                format_satoshis(x)
+            ))
+        }
+        Some(x) => format_satoshis(x),
         None => "".to_string(),
     };
 
-    let mut query_values: Vec<KeyValuePair> = Vec::new();
-    query_values.push(KeyValuePair {
-        key: "label".to_string(),
-        value: l,
-    });
-    query_values.push(KeyValuePair {
-        key: "message".to_string(),
-        value: m,
-    });
-    query_values.push(KeyValuePair {
-        key: "amount".to_string(),
-        value: a,
-    });
+    let mut query_values: Vec<KeyValuePair> = vec![
+        KeyValuePair {
+            key: "label".to_string(),
+            value: l,
+        },
+        KeyValuePair {
+            key: "message".to_string(),
+            value: m,
+        },
+        KeyValuePair {
+            key: "amount".to_string(),
+            value: a,
+        },
+    ];
+    for &(key, value) in extra {
+        lazy_static! {
+            static ref KEY: Regex = Regex::new(r"\A[A-Za-z0-9._-]+\z").unwrap();
+        }
+        if !KEY.is_match(key) {
+            return Err(format!("{:?} is not a valid parameter name", key));
+        }
+        let bare = key.trim_start_matches("req-");
+        // the label, message and amount slots only count when they were given
+        if query_values
+            .iter()
+            .any(|p| !p.value.is_empty() && p.key.trim_start_matches("req-") == bare)
+        {
+            return Err(format!("the parameter {:?} is given twice", bare));
+        }
+        if bare == "pj" {
+            check_https(value, "payjoin endpoint")?;
+        }
+        query_values.push(KeyValuePair {
+            key: key.to_string(),
+            value: uri_escape(value),
+        });
+    }
 
     let joined = query_values
         .iter()
@@ -200,49 +462,3206 @@ pub fn bitcoin_string(
         "".to_string()
     };
 
-    return format!("bitcoin:{}{}", address, query);
+    // cashaddrs keep their prefix, which doubles as the uri scheme of their network
+    if address.contains(':') {
+        return Ok(format!("{}{}", address, query));
+    }
+    Ok(format!("{}:{}{}", scheme.uri_scheme(), address, query))
 }
 
+// parses a decimal coin amount (or an integer of base units) without going through floats
 #[allow(dead_code)]
-pub fn url_string(url: &str) -> String {
-    if !url.starts_with("http") {
-        "http://".to_string() + url
+pub fn parse_satoshis(amount: &str, in_satoshis: bool) -> Result<u64, String> {
+    let err = || format!("{:?} is not a valid amount", amount);
+    let digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    if in_satoshis {
+        if !digits(amount) {
+            return Err(err());
+        }
+        return amount.parse::<u64>().map_err(|_| err());
+    }
+
+    let (whole, frac) = match amount.find('.') {
+        Some(i) => (&amount[..i], &amount[i + 1..]),
+        None => (amount, ""),
+    };
+    let whole = if whole.is_empty() && !frac.is_empty() {
+        "0"
     } else {
-        String::from(url)
+        whole
+    };
+    if !digits(whole) || frac.len() > 8 || !frac.chars().all(|c| c.is_ascii_digit()) {
+        return Err(err());
     }
+    whole
+        .parse::<u64>()
+        .ok()
+        .and_then(|w| w.checked_mul(100_000_000))
+        .and_then(|w| w.checked_add(format!("{:0<8}", frac).parse::<u64>().unwrap()))
+        .ok_or_else(err)
 }
 
+// formats base units as a BIP-21 decimal amount without trailing zeros
 #[allow(dead_code)]
-fn escape_input(inp: &str, simple: bool) -> String {
-    let mut forbidden = Vec::new();
-    if simple {
-        forbidden.push(":");
-    } else {
-        forbidden.push("\\");
-        forbidden.push(";");
-        forbidden.push(",");
-        forbidden.push(":");
+fn format_satoshis(sats: u64) -> String {
+    let amount = format!("{}.{:08}", sats / 100_000_000, sats % 100_000_000);
+    amount
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+// validates an address of the coin and returns it with the networks it can be used on
+#[allow(dead_code)]
+fn coin_address_networks(
+    scheme: &BitcoinScheme,
+    address: &str,
+) -> Result<(String, &'static [BitcoinNetwork]), String> {
+    use self::BitcoinNetwork::*;
+
+    type Networks = &'static [BitcoinNetwork];
+    type Prefixes<'a> = (&'a [(&'a str, Networks)], &'a [(u8, Networks)]);
+    let (segwit, versions): Prefixes = match *scheme {
+        BitcoinScheme::Bitcoin => (
+            &[
+                ("bc", &[Mainnet]),
+                ("tb", &[Testnet, Signet]),
+                ("bcrt", &[Regtest]),
+            ],
+            &[
+                (0x00, &[Mainnet]),
+                (0x05, &[Mainnet]),
+                (0x6f, &[Testnet, Signet, Regtest]),
+                (0xc4, &[Testnet, Signet, Regtest]),
+            ],
+        ),
+        BitcoinScheme::Litecoin => (
+            &[
+                ("ltc", &[Mainnet]),
+                ("tltc", &[Testnet]),
+                ("rltc", &[Regtest]),
+            ],
+            &[
+                (0x30, &[Mainnet]),
+                (0x32, &[Mainnet]),
+                (0x05, &[Mainnet]),
+                (0x6f, &[Testnet, Regtest]),
+                (0x3a, &[Testnet, Regtest]),
+                (0xc4, &[Testnet, Regtest]),
+            ],
+        ),
+        BitcoinScheme::Dogecoin => (
+            &[],
+            &[
+                (0x1e, &[Mainnet]),
+                (0x16, &[Mainnet]),
+                (0x71, &[Testnet, Regtest]),
+                (0xc4, &[Testnet, Regtest]),
+            ],
+        ),
+        BitcoinScheme::BitcoinCash => {
+            // legacy addresses are still valid, everything else is a cashaddr
+            if base58_decode(address).is_some_and(|d| d.len() == 25) {
+                (&[], &[(0x00, &[Mainnet]), (0x05, &[Mainnet])])
+            } else {
+                return cashaddr_networks(address);
+            }
+        }
     };
 
-    let mut n = String::from(inp);
-    for c in &forbidden {
-        n = str::replace(&n, c, &("\\".to_string() + c));
+    let lower = address.to_lowercase();
+    let hrp = lower.rfind('1').map(|i| &lower[..i]);
+    if let Some(&(_, networks)) = segwit.iter().find(|&&(h, _)| Some(h) == hrp) {
+        if address.len() > 90 {
+            return Err("segwit addresses are at most 90 characters long".to_string());
+        }
+        let (_, data, variant) = bech32_decode(address).map_err(|e| {
+            match likely_typo(address, BECH32_ALPHABET, |a| bech32_decode(a).is_ok()) {
+                Some(hint) => format!("{}, {}", e, hint),
+                None => e,
+            }
+        })?;
+        check_witness_program(&data, &variant)?;
+        return Ok((address.to_string(), networks));
+    }
+
+    let decoded = match base58_decode(address) {
+        Some(d) if d.len() == 25 => d,
+        _ => {
+            return Err(format!(
+                "{:?} is not a {} address",
+                address,
+                scheme.uri_scheme()
+            ))
+        }
+    };
+    if !base58_checksum_matches(&decoded) {
+        let hint = likely_typo(address, BASE58_ALPHABET, |a| match base58_decode(a) {
+            Some(d) => d.len() == 25 && base58_checksum_matches(&d),
+            None => false,
+        });
+        return Err(match hint {
+            Some(h) => format!("the checksum of {:?} is invalid, {}", address, h),
+            None => format!("the checksum of {:?} is invalid", address),
+        });
+    }
+    match versions.iter().find(|&&(v, _)| v == decoded[0]) {
+        Some(&(_, networks)) => Ok((address.to_string(), networks)),
+        None => Err(format!(
+            "{:?} is not a {} address (version byte {})",
+            address,
+            scheme.uri_scheme(),
+            decoded[0]
+        )),
     }
-    n
 }
 
+// checks a bitcoin cash cashaddr and returns it with the prefix of its network
 #[allow(dead_code)]
-fn uri_escape(inp: &str) -> String {
-    quote(inp, b"").ok().unwrap()
+fn cashaddr_networks(address: &str) -> Result<(String, &'static [BitcoinNetwork]), String> {
+    use self::BitcoinNetwork::*;
+
+    if address != address.to_lowercase() && address != address.to_uppercase() {
+        return Err("cashaddrs must not mix upper and lower case".to_string());
+    }
+    let lower = address.to_lowercase();
+    let (prefix, payload) = match lower.find(':') {
+        Some(i) => (&lower[..i], &lower[i + 1..]),
+        None => ("bitcoincash", &lower[..]),
+    };
+    let networks: &'static [BitcoinNetwork] = match prefix {
+        "bitcoincash" => &[Mainnet],
+        "bchtest" => &[Testnet],
+        "bchreg" => &[Regtest],
+        _ => return Err(format!("{:?} is not a bitcoin cash address prefix", prefix)),
+    };
+
+    let is_valid = |payload: &str| -> bool {
+        let data: Option<Vec<u8>> = payload
+            .bytes()
+            .map(|c| {
+                BECH32_ALPHABET
+                    .iter()
+                    .position(|&a| a == c)
+                    .map(|v| v as u8)
+            })
+            .collect();
+        match data {
+            Some(ref d) if d.len() > 8 => {
+                let mut values: Vec<u8> = prefix.bytes().map(|b| b & 31).collect();
+                values.push(0);
+                values.extend(d);
+                cashaddr_polymod(&values) == 0
+                    && convert_bits(&d[..d.len() - 8], 5, 8, false)
+                        .is_some_and(|p| p.len() == 21 && (p[0] == 0x00 || p[0] == 0x08))
+            }
+            _ => false,
+        }
+    };
+    if !is_valid(payload) {
+        return Err(match likely_typo(payload, BECH32_ALPHABET, is_valid) {
+            Some(h) => format!("the checksum of {:?} is invalid, {}", address, h),
+            None => format!("{:?} is not a valid cashaddr", address),
+        });
+    }
+    Ok((format!("{}:{}", prefix, payload), networks))
 }
 
 #[allow(dead_code)]
-#[allow(clippy::zero_ptr)]
-fn is_hexstyle(arg: &str) -> bool {
-    lazy_static! {
-        static ref LE: Regex = Regex::new(r"\A\b[0-9a-fA-F]+\b\z").unwrap();
-        static ref RE: Regex = Regex::new(r"\A\b(0[xX])?[0-9a-fA-F]+\b\z").unwrap();
+fn cashaddr_polymod(values: &[u8]) -> u64 {
+    const GENERATOR: [u64; 5] = [
+        0x98_f2bc_8e61,
+        0x79_b76d_99e2,
+        0xf3_3e5f_b3c4,
+        0xae_2eab_e2a8,
+        0x1e_4f43_e470,
+    ];
+    let chk = values.iter().fold(1u64, |chk, &v| {
+        let top = chk >> 35;
+        let chk = ((chk & 0x07_ffff_ffff) << 5) ^ u64::from(v);
+        (0..5).fold(chk, |chk, i| {
+            if (top >> i) & 1 == 1 {
+                chk ^ GENERATOR[i]
+            } else {
+                chk
+            }
+        })
+    });
+    chk ^ 1
+}
+
+#[allow(dead_code)]
+fn base58_checksum_matches(decoded: &[u8]) -> bool {
+    let (payload, checksum) = decoded.split_at(decoded.len() - 4);
+    Sha256::digest(Sha256::digest(payload))[..4] == *checksum
+}
+
+// checks the witness version and program length rules of BIP-141, BIP-173 and BIP-350
+#[allow(dead_code)]
+fn check_witness_program(data: &[u8], variant: &Bech32Variant) -> Result<(), String> {
+    let (version, program) = match data.split_first() {
+        Some((&v, p)) if v <= 16 => (v, convert_bits(p, 5, 8, false)),
+        _ => return Err("the address has no valid witness version".to_string()),
+    };
+    let program = program.ok_or_else(|| "the witness program is malformed".to_string())?;
+
+    match (version, variant) {
+        (0, Bech32Variant::Bech32) if program.len() == 20 || program.len() == 32 => Ok(()),
+        (0, Bech32Variant::Bech32) => Err("segwit v0 programs are 20 or 32 bytes long".to_string()),
+        (0, _) => Err("segwit v0 addresses use bech32, not bech32m".to_string()),
+        (_, Bech32Variant::Bech32m) if (2..=40).contains(&program.len()) => Ok(()),
+        (_, Bech32Variant::Bech32m) => Err("the witness program has an invalid length".to_string()),
+        (_, _) => Err("segwit v1+ addresses use bech32m, not bech32".to_string()),
+    }
+}
+
+// looks for a single character whose replacement would fix the checksum
+#[allow(dead_code)]
+fn likely_typo<F: Fn(&str) -> bool>(inp: &str, alphabet: &[u8], is_valid: F) -> Option<String> {
+    let upper = inp.chars().any(|c| c.is_ascii_uppercase()) && inp.to_uppercase() == inp;
+    let chars: Vec<char> = inp.chars().collect();
+    for (i, &original) in chars.iter().enumerate() {
+        for &a in alphabet {
+            let candidate = if upper {
+                (a as char).to_ascii_uppercase()
+            } else {
+                a as char
+            };
+            if candidate == original {
+                continue;
+            }
+            let mut fixed = chars.clone();
+            fixed[i] = candidate;
+            if is_valid(&fixed.iter().collect::<String>()) {
+                return Some(format!(
+                    "the {:?} at position {} is likely wrong (maybe {:?}?)",
+                    original,
+                    i + 1,
+                    candidate
+                ));
+            }
+        }
+    }
+    None
+}
+
+/// The fields of a SPAYD or PAY by square bank transfer.
+#[derive(Debug, Default)]
+pub struct BankPayment<'a> {
+    pub iban: &'a str,
+    pub bic: Option<&'a str>,
+    pub amount: Option<&'a str>,
+    pub currency: &'a str,
+    pub due_date: Option<&'a str>,
+    pub variable_symbol: Option<&'a str>,
+    pub constant_symbol: Option<&'a str>,
+    pub specific_symbol: Option<&'a str>,
+    pub name: Option<&'a str>,
+    pub message: Option<&'a str>,
+}
+
+// the czech "Short Payment Descriptor"
+#[allow(dead_code)]
+pub fn spayd_string(payment: &BankPayment) -> Result<String, String> {
+    let mut account = normalize_iban(payment.iban)?;
+    if let Some(b) = payment.bic {
+        account = format!("{}+{}", account, normalize_bic(b)?);
+    }
+    let mut fields = vec![
+        "SPD".to_string(),
+        "1.0".to_string(),
+        format!("ACC:{}", account),
+    ];
+
+    if let Some(a) = payment.amount {
+        let cents = parse_cents(a)?;
+        fields.push(format!("AM:{}.{:02}", cents / 100, cents % 100));
+        if fields.last().unwrap().len() > 13 {
+            return Err(format!("the amount {:?} is too large", a));
+        }
+    }
+    fields.push(format!("CC:{}", check_currency(payment.currency)?));
+    if let Some(d) = payment.due_date {
+        fields.push(format!("DT:{}", parse_date(d)?));
+    }
+    if let Some(n) = payment.name {
+        fields.push(format!("RN:{}", spayd_escape(check_length("name", n, 35)?)));
+    }
+    if let Some(m) = payment.message {
+        fields.push(format!(
+            "MSG:{}",
+            spayd_escape(check_length("message", m, 60)?)
+        ));
+    }
+    let symbols = [
+        ("X-VS", "variable symbol", payment.variable_symbol),
+        ("X-SS", "specific symbol", payment.specific_symbol),
+        ("X-KS", "constant symbol", payment.constant_symbol),
+    ];
+    for (key, name, symbol) in symbols.iter() {
+        if let Some(s) = symbol {
+            fields.push(format!("{}:{}", key, check_symbol(name, s, 10)?));
+        }
+    }
+    Ok(fields.join("*"))
+}
+
+// the slovak PAY by square: tab separated fields, a CRC32, LZMA and base32hex
+#[allow(dead_code)]
+pub fn paybysquare_string(payment: &BankPayment) -> Result<String, String> {
+    use lzma_rust2::{LzmaOptions, LzmaWriter};
+    use std::io::Write;
+
+    let amount = match payment.amount {
+        Some(a) => {
+            let cents = parse_cents(a)?;
+            format!("{}.{:02}", cents / 100, cents % 100)
+        }
+        None => "".to_string(),
+    };
+    let symbol = |name, value: Option<&str>, max| match value {
+        Some(s) => check_symbol(name, s, max).map(str::to_string),
+        None => Ok("".to_string()),
+    };
+    let fields = [
+        "".to_string(),  // invoice id
+        "1".to_string(), // one payment
+        "1".to_string(), // a payment order
+        amount,
+        check_currency(payment.currency)?,
+        payment.due_date.map_or(Ok("".to_string()), parse_date)?,
+        symbol("variable symbol", payment.variable_symbol, 10)?,
+        symbol("constant symbol", payment.constant_symbol, 4)?,
+        symbol("specific symbol", payment.specific_symbol, 10)?,
+        "".to_string(), // the symbols in SEPA reference form
+        check_length("message", payment.message.unwrap_or(""), 140)?.to_string(),
+        "1".to_string(), // one bank account
+        normalize_iban(payment.iban)?,
+        payment.bic.map_or(Ok("".to_string()), normalize_bic)?,
+        "0".to_string(), // no standing order
+        "0".to_string(), // no direct debit
+        check_length("name", payment.name.unwrap_or(""), 70)?.to_string(),
+        "".to_string(), // beneficiary address lines
+        "".to_string(),
+    ];
+    let data = fields.join("\t");
+    if data.contains(|c: char| c.is_control() && c != '\t')
+        || fields.iter().any(|f| f.contains('\t'))
+    {
+        return Err("the fields must not contain tabs or other control characters".to_string());
+    }
+
+    let mut total = crc32fast::hash(data.as_bytes()).to_le_bytes().to_vec();
+    total.extend_from_slice(data.as_bytes());
+
+    // the decoders expect raw LZMA1 with a 128 KiB dictionary and the default lc/lp/pb
+    let mut options = LzmaOptions::with_preset(6);
+    options.dict_size = 128 * 1024;
+    let mut compressed = vec![0, 0];
+    compressed.extend_from_slice(&(total.len() as u16).to_le_bytes());
+    let compressed = (|| {
+        let mut writer = LzmaWriter::new_no_header(compressed, &options, true)?;
+        writer.write_all(&total)?;
+        writer.finish()
+    })()
+    .map_err(|e| format!("could not compress the payment: {}", e))?;
+
+    Ok(base32hex_encode(&compressed))
+}
+
+#[allow(dead_code)]
+fn check_currency(currency: &str) -> Result<String, String> {
+    if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(format!(
+            "{:?} is not a three letter currency code",
+            currency
+        ));
+    }
+    Ok(currency.to_uppercase())
+}
+
+#[allow(dead_code)]
+fn check_symbol<'a>(name: &str, symbol: &'a str, max: usize) -> Result<&'a str, String> {
+    if symbol.is_empty() || symbol.len() > max || !symbol.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("the {} can only be 1 to {} digits", name, max));
+    }
+    Ok(symbol)
+}
+
+// accepts YYYY-MM-DD and YYYYMMDD and returns the latter
+#[allow(dead_code)]
+fn parse_date(date: &str) -> Result<String, String> {
+    let digits = date.replace("-", "");
+    let is_valid = digits.len() == 8 && digits.chars().all(|c| c.is_ascii_digit()) && {
+        let year = digits[..4].parse::<u32>().unwrap();
+        let month = digits[4..6].parse::<u32>().unwrap();
+        let day = digits[6..8].parse::<u32>().unwrap();
+        let is_leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+        let days = match month {
+            2 if is_leap => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            1..=12 => 31,
+            _ => 0,
+        };
+        (1..=days).contains(&day)
+    };
+    if !is_valid {
+        return Err(format!("{:?} is not a date like 2024-01-31", date));
+    }
+    Ok(digits)
+}
+
+// '*' separates the SPAYD fields and has to be percent encoded
+#[allow(dead_code)]
+fn spayd_escape(inp: &str) -> String {
+    inp.replace("%", "%25").replace("*", "%2A")
+}
+
+/// The fields of an EPC069-12 ("GiroCode") SEPA credit transfer.
+#[derive(Debug, Default)]
+pub struct Giro<'a> {
+    pub version: &'a str,
+    pub charset: &'a str,
+    pub bic: Option<&'a str>,
+    pub name: &'a str,
+    pub iban: &'a str,
+    pub amount: Option<&'a str>,
+    pub purpose: Option<&'a str>,
+    pub reference: Option<&'a str>,
+    pub text: Option<&'a str>,
+    pub information: Option<&'a str>,
+}
+
+#[allow(dead_code)]
+pub fn giro_string(giro: &Giro) -> Result<String, String> {
+    if giro.version != "001" && giro.version != "002" {
+        return Err(format!("unknown GiroCode version {:?}", giro.version));
+    }
+    let charset = match giro.charset.parse::<u8>() {
+        Ok(c) if (1..=8).contains(&c) => c,
+        _ => return Err(format!("unknown GiroCode charset {:?}", giro.charset)),
+    };
+
+    let bic = match giro.bic {
+        Some(b) => normalize_bic(b)?,
+        None if giro.version == "001" => {
+            return Err("a BIC is required for GiroCode version 001".to_string())
+        }
+        None => "".to_string(),
+    };
+    let iban = normalize_iban(giro.iban)?;
+    let amount = match giro.amount {
+        Some(a) => {
+            let cents = parse_cents(a)?;
+            if !(1..=99_999_999_999).contains(&cents) {
+                return Err("the amount must be between 0.01 and 999999999.99 EUR".to_string());
+            }
+            format!("EUR{}.{:02}", cents / 100, cents % 100)
+        }
+        None => "".to_string(),
+    };
+    let purpose = match giro.purpose {
+        Some(p) if p.len() == 4 && p.chars().all(|c| c.is_ascii_alphanumeric()) => p.to_uppercase(),
+        Some(p) => return Err(format!("{:?} is not a four character purpose code", p)),
+        None => "".to_string(),
+    };
+    if giro.reference.is_some() && giro.text.is_some() {
+        return Err("use either a remittance reference or a remittance text, not both".to_string());
+    }
+
+    let name = check_length("name", giro.name, 70)?;
+    if name.is_empty() {
+        return Err("the beneficiary name must not be empty".to_string());
+    }
+    let reference = check_length("reference", giro.reference.unwrap_or(""), 35)?;
+    let text = check_length("text", giro.text.unwrap_or(""), 140)?;
+    let information = check_length("information", giro.information.unwrap_or(""), 70)?;
+
+    // only UTF-8 is written as-is, every other charset is safe for plain ascii only
+    if charset != 1 {
+        let fields = [name, reference, text, information];
+        if !fields.iter().all(|f| f.is_ascii()) {
+            return Err("non-ascii text needs charset 1 (UTF-8)".to_string());
+        }
+    }
+
+    let lines = [
+        "BCD",
+        giro.version,
+        &charset.to_string(),
+        "SCT",
+        &bic,
+        name,
+        &iban,
+        &amount,
+        &purpose,
+        reference,
+        text,
+        information,
+    ];
+    // trailing empty fields may be left out
+    let used = lines.iter().rposition(|l| !l.is_empty()).unwrap_or(0) + 1;
+    let payload = lines[..used].join("\n");
+
+    if payload.len() > 331 {
+        return Err(format!(
+            "the payload is {} bytes long, but GiroCodes are limited to 331 bytes",
+            payload.len()
+        ));
+    }
+    Ok(payload)
+}
+
+/// A structured postal address as used by the Swiss QR-bill.
+#[derive(Debug, Default)]
+pub struct SwissAddress<'a> {
+    pub name: &'a str,
+    pub street: &'a str,
+    pub building: &'a str,
+    pub postcode: &'a str,
+    pub town: &'a str,
+    pub country: &'a str,
+}
+
+/// The fields of a Swiss QR-bill (SPC) payment part.
+#[derive(Debug, Default)]
+pub struct SwissQr<'a> {
+    pub iban: &'a str,
+    pub creditor: SwissAddress<'a>,
+    pub amount: Option<&'a str>,
+    pub currency: &'a str,
+    pub debtor: Option<SwissAddress<'a>>,
+    pub reference: Option<&'a str>,
+    pub message: Option<&'a str>,
+    pub billing: Option<&'a str>,
+}
+
+#[allow(dead_code)]
+pub fn swissqr_string(bill: &SwissQr) -> Result<String, String> {
+    let iban = normalize_iban(bill.iban)?;
+    if !iban.starts_with("CH") && !iban.starts_with("LI") {
+        return Err("QR-bills need a Swiss or Liechtenstein IBAN".to_string());
+    }
+    // QR-IBANs carry an institution id between 30000 and 31999
+    let is_qr_iban = match iban[4..9].parse::<u32>() {
+        Ok(iid) => (30000..=31999).contains(&iid),
+        Err(_) => false,
+    };
+
+    let amount = match bill.amount {
+        Some(a) => {
+            let cents = parse_cents(a)?;
+            if !(1..=99_999_999_999).contains(&cents) {
+                return Err("the amount must be between 0.01 and 999999999.99".to_string());
+            }
+            format!("{}.{:02}", cents / 100, cents % 100)
+        }
+        None => "".to_string(),
+    };
+    if bill.currency != "CHF" && bill.currency != "EUR" {
+        return Err(format!("{:?} is not CHF or EUR", bill.currency));
+    }
+
+    let (reference_type, reference) = match bill.reference {
+        Some(r) => {
+            let r = r.replace(" ", "").to_uppercase();
+            if is_qr_iban {
+                if r.len() != 27 || !r.chars().all(|c| c.is_ascii_digit()) {
+                    return Err("a QR reference consists of exactly 27 digits".to_string());
+                }
+                if mod10_recursive(&r[..26]) != r[26..].parse::<u32>().unwrap() {
+                    return Err(format!(
+                        "the QR reference {:?} has an invalid check digit",
+                        r
+                    ));
+                }
+                ("QRR", r)
+            } else {
+                lazy_static! {
+                    static ref SCOR: Regex = Regex::new(r"\ARF[0-9]{2}[A-Z0-9]{1,21}\z").unwrap();
+                }
+                if !SCOR.is_match(&r) {
+                    return Err(format!("{:?} is not an ISO 11649 creditor reference", r));
+                }
+                if mod97(&r) != 1 {
+                    return Err(format!(
+                        "the creditor reference {:?} has an invalid checksum",
+                        r
+                    ));
+                }
+                ("SCOR", r)
+            }
+        }
+        None if is_qr_iban => return Err("a QR-IBAN needs a QR reference".to_string()),
+        None => ("NON", "".to_string()),
+    };
+
+    let mut lines = vec!["SPC", "0200", "1", &iban];
+    let creditor = swiss_address_lines(&bill.creditor)?;
+    lines.extend(creditor.iter().map(|l| l.as_str()));
+    // the ultimate creditor is reserved for future use and stays empty
+    lines.extend(&["", "", "", "", "", "", ""]);
+    lines.push(&amount);
+    lines.push(bill.currency);
+    let debtor = match bill.debtor {
+        Some(ref d) => swiss_address_lines(d)?,
+        None => vec!["".to_string(); 7],
+    };
+    lines.extend(debtor.iter().map(|l| l.as_str()));
+    lines.push(reference_type);
+    lines.push(&reference);
+    lines.push(check_length("message", bill.message.unwrap_or(""), 140)?);
+    lines.push("EPD");
+    if let Some(b) = bill.billing {
+        lines.push(check_length("billing information", b, 140)?);
+    }
+
+    let payload = lines.join("\n");
+    if payload.chars().count() > 997 {
+        return Err("the payload is longer than the 997 characters a QR-bill allows".to_string());
     }
+    Ok(payload)
+}
+
+#[allow(dead_code)]
+fn swiss_address_lines(address: &SwissAddress) -> Result<Vec<String>, String> {
+    if address.name.is_empty() || address.town.is_empty() || address.postcode.is_empty() {
+        return Err("an address needs at least a name, a postcode and a town".to_string());
+    }
+    if address.country.len() != 2 || !address.country.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(format!(
+            "{:?} is not a two letter country code",
+            address.country
+        ));
+    }
+
+    Ok(vec![
+        "S".to_string(),
+        check_length("name", address.name, 70)?.to_string(),
+        check_length("street", address.street, 70)?.to_string(),
+        check_length("building number", address.building, 16)?.to_string(),
+        check_length("postcode", address.postcode, 16)?.to_string(),
+        check_length("town", address.town, 35)?.to_string(),
+        address.country.to_uppercase(),
+    ])
+}
+
+#[derive(Debug)]
+#[allow(dead_code)]
+pub enum OtpKind {
+    Totp { period: u64 },
+    Hotp { counter: u64 },
+}
+
+#[derive(Debug)]
+#[allow(dead_code)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum OtpAlgorithm {
+    SHA1,
+    SHA256,
+    SHA512,
+}
+
+#[allow(dead_code)]
+pub fn otp_string(
+    kind: &OtpKind,
+    issuer: Option<&str>,
+    account: &str,
+    secret: &str,
+    algorithm: &OtpAlgorithm,
+    digits: u32,
+) -> Result<String, String> {
+    if account.is_empty() || account.contains(':') {
+        return Err("the account name must not be empty or contain a colon".to_string());
+    }
+    if !(6..=8).contains(&digits) {
+        return Err("one-time passwords have 6 to 8 digits".to_string());
+    }
+    let secret = normalize_otp_secret(secret)?;
+
+    let (label, mut query) = match issuer {
+        Some(i) if i.contains(':') => {
+            return Err("the issuer must not contain a colon".to_string());
+        }
+        Some(i) => (
+            format!("{}:{}", uri_escape(i), uri_escape(account)),
+            format!("secret={}&issuer={}", secret, uri_escape(i)),
+        ),
+        None => (uri_escape(account), format!("secret={}", secret)),
+    };
+    query += &format!("&algorithm={:?}&digits={}", algorithm, digits);
+
+    let otp_type = match *kind {
+        OtpKind::Totp { period: 0 } => return Err("the period must not be zero".to_string()),
+        OtpKind::Totp { period } => {
+            query += &format!("&period={}", period);
+            "totp"
+        }
+        OtpKind::Hotp { counter } => {
+            query += &format!("&counter={}", counter);
+            "hotp"
+        }
+    };
+
+    Ok(format!("otpauth://{}/{}?{}", otp_type, label, query))
+}
 
-    LE.is_match(arg) || RE.is_match(arg)
+// creates a random base32 secret as long as the digest of the algorithm
+#[allow(dead_code)]
+pub fn otp_generate_secret(algorithm: &OtpAlgorithm) -> Result<String, String> {
+    let mut key = vec![
+        0u8;
+        match *algorithm {
+            OtpAlgorithm::SHA1 => 20,
+            OtpAlgorithm::SHA256 => 32,
+            OtpAlgorithm::SHA512 => 64,
+        }
+    ];
+    getrandom::getrandom(&mut key).map_err(|e| e.to_string())?;
+    Ok(base32_encode(&key))
+}
+
+// computes the RFC 4226 one-time password for the given counter (or time step)
+#[allow(dead_code)]
+pub fn otp_code(
+    secret: &str,
+    algorithm: &OtpAlgorithm,
+    digits: u32,
+    counter: u64,
+) -> Result<String, String> {
+    let key = base32_decode(&normalize_otp_secret(secret)?).unwrap();
+    let message = counter.to_be_bytes();
+    let hash = match *algorithm {
+        OtpAlgorithm::SHA1 => hmac_digest::<Hmac<Sha1>>(&key, &message),
+        OtpAlgorithm::SHA256 => hmac_digest::<Hmac<Sha256>>(&key, &message),
+        OtpAlgorithm::SHA512 => hmac_digest::<Hmac<Sha512>>(&key, &message),
+    };
+
+    // dynamic truncation takes four bytes from the offset in the lowest nibble
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let mut bytes = [0u8; 4];
+    bytes.copy_from_slice(&hash[offset..offset + 4]);
+    let value = u32::from_be_bytes(bytes) & 0x7fff_ffff;
+
+    Ok(format!(
+        "{:0width$}",
+        value % 10u32.pow(digits),
+        width = digits as usize
+    ))
+}
+
+#[allow(dead_code)]
+fn hmac_digest<M: Mac + hmac::digest::KeyInit>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = <M as hmac::digest::KeyInit>::new_from_slice(key).unwrap();
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+// uppercases the secret and drops spaces and padding, which authenticator apps dislike
+#[allow(dead_code)]
+fn normalize_otp_secret(secret: &str) -> Result<String, String> {
+    let n = secret
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '=')
+        .collect::<String>()
+        .to_uppercase();
+    match base32_decode(&n) {
+        Some(ref key) if !key.is_empty() => Ok(n),
+        _ => Err(format!("{:?} is not a base32 encoded secret", secret)),
+    }
+}
+
+#[allow(dead_code)]
+pub fn ethereum_string(
+    address: &str,
+    chain_id: Option<u64>,
+    value: Option<&str>,
+    token: Option<(&str, &str)>,
+) -> Result<String, String> {
+    let chain = match chain_id {
+        Some(id) => format!("@{}", id),
+        None => "".to_string(),
+    };
+
+    match token {
+        // ERC-20 transfers call the token contract with the recipient as parameter
+        Some((contract, amount)) => {
+            if value.is_some() {
+                return Err("token transfers can not also send ether".to_string());
+            }
+            Ok(format!(
+                "ethereum:{}{}/transfer?address={}&uint256={}",
+                eip55_checksum(contract)?,
+                chain,
+                eip55_checksum(address)?,
+                parse_uint256(amount)?
+            ))
+        }
+        None => {
+            let query = match value {
+                Some(v) => format!("?value={}", parse_uint256(v)?),
+                None => "".to_string(),
+            };
+            Ok(format!(
+                "ethereum:{}{}{}",
+                eip55_checksum(address)?,
+                chain,
+                query
+            ))
+        }
+    }
+}
+
+// converts a decimal ether amount into an exact wei integer
+#[allow(dead_code)]
+pub fn ether_to_wei(ether: &str) -> Result<String, String> {
+    let err = || format!("{:?} is not a valid ether amount", ether);
+    let (whole, frac) = match ether.find('.') {
+        Some(i) => (&ether[..i], &ether[i + 1..]),
+        None => (ether, ""),
+    };
+    let digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    if (whole.is_empty() && frac.is_empty()) || frac.len() > 18 || !digits(whole) || !digits(frac) {
+        return Err(err());
+    }
+    parse_uint256(&format!("{}{:0<18}", whole, frac))
+}
+
+// checks an exact unsigned integer against the uint256 range and strips leading zeros
+#[allow(dead_code)]
+fn parse_uint256(inp: &str) -> Result<String, String> {
+    const MAX: &str =
+        "115792089237316195423570985348841195423570985008687907853269984665640564039457584007913129639935";
+
+    if inp.is_empty() || !inp.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("{:?} is not an unsigned integer", inp));
+    }
+    let n = inp.trim_start_matches('0');
+    let n = if n.is_empty() { "0" } else { n };
+    if n.len() > MAX.len() || (n.len() == MAX.len() && n > MAX) {
+        return Err(format!("{} does not fit into an uint256", n));
+    }
+    Ok(n.to_string())
+}
+
+// validates an ethereum address and returns it with its EIP-55 mixed-case checksum
+#[allow(dead_code)]
+fn eip55_checksum(address: &str) -> Result<String, String> {
+    lazy_static! {
+        static ref ADDRESS: Regex = Regex::new(r"\A0x[0-9a-fA-F]{40}\z").unwrap();
+    }
+    if !ADDRESS.is_match(address) {
+        return Err(format!(
+            "{:?} is not a hex encoded ethereum address",
+            address
+        ));
+    }
+
+    let hex = address[2..].to_lowercase();
+    let hash = Keccak256::digest(hex.as_bytes());
+    let checksummed = hex
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0x0f;
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect::<String>();
+
+    // all lower or all upper case addresses carry no checksum
+    let plain = &address[2..];
+    let is_mixed = plain != plain.to_lowercase() && plain != plain.to_uppercase();
+    if is_mixed && plain != checksummed {
+        return Err(format!(
+            "the address {:?} has an invalid EIP-55 checksum, expected 0x{}",
+            address, checksummed
+        ));
+    }
+    Ok(format!("0x{}", checksummed))
+}
+
+// encodes a BOLT11 invoice or a LNURL in upper case, so the code can use alphanumeric mode
+#[allow(dead_code)]
+pub fn lightning_string(request: &str, fallback: Option<&str>) -> Result<String, String> {
+    let request = request.trim();
+    let request = if request.to_lowercase().starts_with("lightning:") {
+        &request[10..]
+    } else {
+        request
+    };
+
+    let lower = request.to_lowercase();
+    let mut network = None;
+    let (encoded, amount) = if lower.starts_with("https://") || lower.starts_with("http://") {
+        (lnurl_encode(request)?, None)
+    } else {
+        let (hrp, data, variant) = bech32_decode(request)?;
+        if variant != Bech32Variant::Bech32 {
+            return Err("lightning requests use bech32, not bech32m".to_string());
+        }
+        if hrp == "lnurl" {
+            let url = convert_bits(&data, 5, 8, false)
+                .and_then(|bytes| String::from_utf8(bytes).ok())
+                .ok_or_else(|| "the LNURL does not contain a valid url".to_string())?;
+            check_https(&url, "LNURL endpoint")?;
+            (request.to_uppercase(), None)
+        } else {
+            let amount = bolt11_amount(&hrp)?;
+            network = Some(bolt11_network(&hrp));
+            (request.to_uppercase(), amount)
+        }
+    };
+
+    match fallback {
+        // BIP-21 unified codes keep the on-chain address as the fallback
+        Some(address) => {
+            let (address, networks) = coin_address_networks(&BitcoinScheme::Bitcoin, address)
+                .map_err(|e| format!("invalid fallback address: {}", e))?;
+            if let Some(n) = network.filter(|n| !networks.contains(n)) {
+                return Err(format!(
+                    "the fallback address {:?} can not be used on {:?}, the network of the invoice",
+                    address, n
+                ));
+            }
+            let lower = address.to_lowercase();
+            let is_bech32 = ["bc1", "tb1", "bcrt1"].iter().any(|p| lower.starts_with(p));
+            let address = if is_bech32 {
+                address.to_uppercase()
+            } else {
+                address
+            };
+            let amount = match amount {
+                Some(a) => format!("amount={}&", a),
+                None => "".to_string(),
+            };
+            Ok(format!(
+                "bitcoin:{}?{}lightning={}",
+                address, amount, encoded
+            ))
+        }
+        None => Ok(format!("LIGHTNING:{}", encoded)),
+    }
+}
+
+#[allow(dead_code)]
+fn lnurl_encode(url: &str) -> Result<String, String> {
+    check_https(url, "LNURL endpoint")?;
+    let data = convert_bits(url.as_bytes(), 8, 5, true).unwrap();
+    Ok(bech32_encode("lnurl", &data, &Bech32Variant::Bech32).to_uppercase())
+}
+
+// endpoints must use https, only onion services may use plain http
+#[allow(dead_code)]
+fn check_https(url: &str, what: &str) -> Result<(), String> {
+    let lower = url.to_lowercase();
+    let is_onion = lower.starts_with("http://")
+        && lower[7..]
+            .split(['/', ':', '?'])
+            .next()
+            .is_some_and(|host| host.ends_with(".onion"));
+    if lower.starts_with("https://") || is_onion {
+        Ok(())
+    } else {
+        Err(format!("the {} {:?} has to use https", what, url))
+    }
+}
+
+// the network of a BOLT11 invoice, the prefix was already checked by bolt11_amount
+#[allow(dead_code)]
+fn bolt11_network(hrp: &str) -> BitcoinNetwork {
+    if hrp.starts_with("lnbcrt") {
+        BitcoinNetwork::Regtest
+    } else if hrp.starts_with("lnbc") {
+        BitcoinNetwork::Mainnet
+    } else if hrp.starts_with("lntbs") {
+        BitcoinNetwork::Signet
+    } else {
+        BitcoinNetwork::Testnet
+    }
+}
+
+// checks the human-readable part of a BOLT11 invoice and returns its amount in BTC
+#[allow(dead_code)]
+fn bolt11_amount(hrp: &str) -> Result<Option<String>, String> {
+    lazy_static! {
+        static ref HRP: Regex = Regex::new(r"\Aln(bcrt|bc|tbs|tb)([0-9]*)([munp]?)\z").unwrap();
+    }
+    let caps = match HRP.captures(hrp) {
+        Some(c) => c,
+        None => return Err(format!("{:?} is not a known lightning invoice prefix", hrp)),
+    };
+    let (digits, multiplier) = (&caps[2], &caps[3]);
+    if digits.is_empty() {
+        if !multiplier.is_empty() {
+            return Err("the invoice amount is missing its digits".to_string());
+        }
+        return Ok(None);
+    }
+
+    // work in pico-bitcoin to stay exact, then only hand out whole satoshis
+    let pico_per_unit: u128 = match multiplier {
+        "m" => 1_000_000_000,
+        "u" => 1_000_000,
+        "n" => 1_000,
+        "p" => 1,
+        _ => 1_000_000_000_000,
+    };
+    let pico = digits
+        .parse::<u128>()
+        .ok()
+        .and_then(|d| d.checked_mul(pico_per_unit))
+        .ok_or_else(|| "the invoice amount is too large".to_string())?;
+    if pico % 10_000 != 0 {
+        return Ok(None);
+    }
+    Ok(u64::try_from(pico / 10_000).ok().map(format_satoshis))
+}
+
+/// The fields of a Wi-Fi Easy Connect (DPP) bootstrapping URI.
+#[derive(Debug, Default)]
+pub struct Dpp<'a> {
+    pub channels: Option<&'a str>,
+    pub mac: Option<&'a str>,
+    pub info: Option<&'a str>,
+    pub version: Option<&'a str>,
+    pub host: Option<&'a str>,
+    pub key: &'a str,
+}
+
+#[allow(dead_code)]
+pub fn dpp_string(dpp: &Dpp) -> Result<String, String> {
+    let mut fields = Vec::new();
+
+    if let Some(c) = dpp.channels {
+        // a list of operating class/channel pairs, e.g. 81/1,115/36
+        let is_valid = |pair: &str| {
+            let mut parts = pair.splitn(2, '/');
+            let mut number = || parts.next().and_then(|p| p.parse::<u8>().ok());
+            matches!((number(), number()), (Some(class), Some(channel)) if class > 0 && channel > 0)
+        };
+        if !c.split(',').all(is_valid) {
+            return Err(format!("{:?} is not a list of class/channel pairs", c));
+        }
+        fields.push(format!("C:{}", c));
+    }
+    if let Some(m) = dpp.mac {
+        let hex = m.replace(":", "").replace("-", "").to_lowercase();
+        if hex.len() != 12 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("{:?} is not a MAC address", m));
+        }
+        fields.push(format!("M:{}", hex));
+    }
+    if let Some(v) = dpp.version {
+        if v.is_empty() || !v.chars().all(|c| c.is_ascii_digit()) {
+            return Err(format!("{:?} is not a DPP version number", v));
+        }
+        fields.push(format!("V:{}", v));
+    }
+    if let Some(h) = dpp.host {
+        lazy_static! {
+            static ref HOST: Regex = Regex::new(r"\A[A-Za-z0-9.:\[\]-]+\z").unwrap();
+        }
+        if !HOST.is_match(h) {
+            return Err(format!("{:?} is not a host name or address", h));
+        }
+        fields.push(format!("H:{}", h));
+    }
+    if let Some(i) = dpp.info {
+        if !i.bytes().all(|b| (0x20..=0x7e).contains(&b) && b != b';') {
+            return Err("the info may only contain printable ascii without ';'".to_string());
+        }
+        fields.push(format!("I:{}", i));
+    }
+
+    dpp_check_key(dpp.key)?;
+    fields.push(format!("K:{}", dpp.key));
+
+    Ok(format!("DPP:{};;", fields.join(";")))
+}
+
+// creates a P-256 key pair, returning the private key as PKCS#8 PEM and the DER public key
+#[allow(dead_code)]
+pub fn dpp_generate_key() -> Result<(String, String), String> {
+    use p256::pkcs8::{EncodePrivateKey, EncodePublicKey, LineEnding};
+
+    // retry in the astronomically unlikely case the bytes are not a valid scalar
+    let secret = loop {
+        let mut bytes = [0u8; 32];
+        getrandom::getrandom(&mut bytes).map_err(|e| e.to_string())?;
+        if let Ok(k) = p256::SecretKey::from_slice(&bytes) {
+            break k;
+        }
+    };
+    let pem = secret
+        .to_pkcs8_pem(LineEnding::LF)
+        .map_err(|e| e.to_string())?;
+    let der = secret
+        .public_key()
+        .to_public_key_der()
+        .map_err(|e| e.to_string())?;
+    Ok((pem.to_string(), base64_encode(der.as_bytes(), false)))
+}
+
+// the key is a DER SubjectPublicKeyInfo of an elliptic curve key
+#[allow(dead_code)]
+fn dpp_check_key(key: &str) -> Result<(), String> {
+    use p256::pkcs8::DecodePublicKey;
+
+    const EC_PUBLIC_KEY: &[u8] = &[0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+    const PRIME256V1: &[u8] = &[0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+
+    let der = match base64_decode(key) {
+        Some(d) if key.bytes().all(|b| !b.is_ascii_whitespace()) => d,
+        _ => return Err("the public key has to be base64 encoded".to_string()),
+    };
+    let contains = |needle: &[u8]| der.windows(needle.len()).any(|w| w == needle);
+    if der.first() != Some(&0x30) || !contains(EC_PUBLIC_KEY) {
+        return Err("the public key is not a DER encoded elliptic curve key".to_string());
+    }
+    // P-256 is by far the most common curve, so its point can be checked as well
+    if contains(PRIME256V1) && p256::PublicKey::from_public_key_der(&der).is_err() {
+        return Err("the public key is not a valid P-256 point".to_string());
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+#[allow(dead_code)]
+pub enum MatterFlow {
+    Standard,
+    UserIntent,
+    Custom,
+}
+
+#[derive(Debug)]
+#[allow(dead_code)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum MatterDiscovery {
+    SoftAP,
+    BLE,
+    OnNetwork,
+}
+
+/// The onboarding data of a Matter device.
+#[derive(Debug)]
+pub struct Matter<'a> {
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub flow: MatterFlow,
+    pub discovery: &'a [MatterDiscovery],
+    pub discriminator: u16,
+    pub passcode: u32,
+}
+
+#[allow(dead_code)]
+pub fn matter_string(matter: &Matter) -> Result<String, String> {
+    check_matter(matter)?;
+    let discovery = matter.discovery.iter().fold(0, |bits, d| {
+        bits | match d {
+            MatterDiscovery::SoftAP => 1,
+            MatterDiscovery::BLE => 2,
+            MatterDiscovery::OnNetwork => 4,
+        }
+    });
+
+    // version, vendor, product, flow, discovery, discriminator, passcode and padding,
+    // packed least significant bit first into 88 bits
+    let fields: [(u64, u32); 8] = [
+        (0, 3),
+        (u64::from(matter.vendor_id), 16),
+        (u64::from(matter.product_id), 16),
+        (matter_flow_value(&matter.flow), 2),
+        (discovery, 8),
+        (u64::from(matter.discriminator), 12),
+        (u64::from(matter.passcode), 27),
+        (0, 4),
+    ];
+    let mut bits = 0u128;
+    let mut offset = 0;
+    for (value, width) in fields.iter() {
+        bits |= u128::from(*value) << offset;
+        offset += width;
+    }
+    let bytes = &bits.to_le_bytes()[..11];
+
+    Ok(format!("MT:{}", base38_encode(bytes)))
+}
+
+// the digits printed next to the code for manual entry, 21 digits long for custom flows
+#[allow(dead_code)]
+pub fn matter_manual_code(matter: &Matter) -> Result<String, String> {
+    check_matter(matter)?;
+    let short_discriminator = u32::from(matter.discriminator >> 8);
+    let is_custom = matches!(matter.flow, MatterFlow::Custom);
+
+    let mut code = format!(
+        "{}{:05}{:04}",
+        (u32::from(is_custom) << 2) | (short_discriminator >> 2),
+        ((short_discriminator & 3) << 14) | (matter.passcode & 0x3fff),
+        matter.passcode >> 14
+    );
+    if is_custom {
+        code.push_str(&format!("{:05}{:05}", matter.vendor_id, matter.product_id));
+    }
+    code.push_str(&verhoeff(&code).to_string());
+    Ok(code)
+}
+
+#[allow(dead_code)]
+fn matter_flow_value(flow: &MatterFlow) -> u64 {
+    match flow {
+        MatterFlow::Standard => 0,
+        MatterFlow::UserIntent => 1,
+        MatterFlow::Custom => 2,
+    }
+}
+
+#[allow(dead_code)]
+fn check_matter(matter: &Matter) -> Result<(), String> {
+    // trivial passcodes are forbidden by the spec
+    const FORBIDDEN: [u32; 12] = [
+        0, 11111111, 22222222, 33333333, 44444444, 55555555, 66666666, 77777777, 88888888,
+        99999999, 12345678, 87654321,
+    ];
+    if matter.passcode > 99999998 || FORBIDDEN.contains(&matter.passcode) {
+        return Err(format!("{:08} is not an allowed passcode", matter.passcode));
+    }
+    if matter.discriminator > 0xfff {
+        return Err(format!(
+            "the discriminator {} does not fit into 12 bits",
+            matter.discriminator
+        ));
+    }
+    if matter.discovery.is_empty() {
+        return Err("at least one discovery capability is needed".to_string());
+    }
+    Ok(())
+}
+
+#[allow(dead_code)]
+pub fn esim_string(
+    smdp: &str,
+    matching_id: &str,
+    oid: Option<&str>,
+    confirmation_code: bool,
+) -> Result<String, String> {
+    lazy_static! {
+        static ref HOST: Regex = Regex::new(
+            r"(?i)\A([a-z0-9]([a-z0-9-]{0,61}[a-z0-9])?\.)+[a-z]([a-z0-9-]{0,61}[a-z0-9])?\z"
+        )
+        .unwrap();
+        static ref MATCHING_ID: Regex = Regex::new(r"(?i)\A[0-9a-z-]*\z").unwrap();
+        static ref OID: Regex = Regex::new(r"\A[0-2](\.(0|[1-9][0-9]*))+\z").unwrap();
+    }
+    // host names and matching ids are case-insensitive, uppercase keeps the code in alphanumeric mode
+    if smdp.len() > 255 || !HOST.is_match(smdp) {
+        return Err(format!("{:?} is not a valid SM-DP+ address", smdp));
+    }
+    if !MATCHING_ID.is_match(matching_id) {
+        return Err(format!(
+            "the matching id {:?} may only contain letters, digits and '-'",
+            matching_id
+        ));
+    }
+    let mut code = format!(
+        "LPA:1${}${}",
+        smdp.to_uppercase(),
+        matching_id.to_uppercase()
+    );
+
+    if let Some(o) = oid {
+        if !OID.is_match(o) {
+            return Err(format!("{:?} is not a valid object identifier", o));
+        }
+    }
+    if oid.is_some() || confirmation_code {
+        code.push_str(&format!("${}", oid.unwrap_or("")));
+    }
+    if confirmation_code {
+        code.push_str("$1");
+    }
+    Ok(code)
+}
+
+/// The parts of a wg-quick tunnel that can be given on the command line.
+#[derive(Debug, Default)]
+pub struct Wireguard<'a> {
+    pub private_key: &'a str,
+    pub addresses: Vec<&'a str>,
+    pub dns: Vec<&'a str>,
+    pub peer_public_key: &'a str,
+    pub preshared_key: Option<&'a str>,
+    pub endpoint: &'a str,
+    pub allowed_ips: Vec<&'a str>,
+    pub keepalive: Option<&'a str>,
+}
+
+#[allow(dead_code)]
+pub fn wireguard_string(wg: &Wireguard) -> Result<String, String> {
+    let mut config = vec!["[Interface]".to_string()];
+    config.push(format!("PrivateKey = {}", wg.private_key));
+    config.push(format!("Address = {}", wg.addresses.join(", ")));
+    if !wg.dns.is_empty() {
+        config.push(format!("DNS = {}", wg.dns.join(", ")));
+    }
+    config.push("[Peer]".to_string());
+    config.push(format!("PublicKey = {}", wg.peer_public_key));
+    if let Some(k) = wg.preshared_key {
+        config.push(format!("PresharedKey = {}", k));
+    }
+    config.push(format!("Endpoint = {}", wg.endpoint));
+    config.push(format!("AllowedIPs = {}", wg.allowed_ips.join(", ")));
+    if let Some(k) = wg.keepalive {
+        config.push(format!("PersistentKeepalive = {}", k));
+    }
+    wireguard_config(&config.join("\n"))
+}
+
+// checks a wg-quick config and strips comments and blank lines, which only cost modules
+#[allow(dead_code)]
+pub fn wireguard_config(content: &str) -> Result<String, String> {
+    let mut lines = Vec::new();
+    let mut section = String::new();
+    let mut has_private_key = false;
+    let mut peers = 0;
+    for (number, line) in content.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with('[') {
+            section = line.to_lowercase();
+            if section == "[peer]" {
+                peers += 1;
+            }
+            lines.push(line.to_string());
+            continue;
+        }
+
+        // the line itself may hold the private key, so only its number is reported
+        let (key, value) = match line.find('=') {
+            Some(i) => (line[..i].trim(), line[i + 1..].trim()),
+            None => return Err(format!("line {} is not a key = value line", number + 1)),
+        };
+        if section.is_empty() {
+            return Err(format!("line {} is outside of any section", number + 1));
+        }
+        let lower = key.to_lowercase();
+        if lower == "privatekey" || lower == "publickey" || lower == "presharedkey" {
+            check_wireguard_key(key, value)?;
+        }
+        if lower == "privatekey" && section == "[interface]" {
+            has_private_key = true;
+        }
+        if value.is_empty() {
+            return Err(format!("line {} has no value", number + 1));
+        }
+        lines.push(format!("{} = {}", key, value));
+    }
+
+    if !has_private_key {
+        return Err("the config needs an [Interface] with a PrivateKey".to_string());
+    }
+    if peers == 0 {
+        return Err("the config needs at least one [Peer]".to_string());
+    }
+    Ok(lines.join("\n"))
+}
+
+// the private and preshared keys of a config, which must never be shown
+#[allow(dead_code)]
+pub fn wireguard_secrets(config: &str) -> Vec<String> {
+    config
+        .lines()
+        .filter_map(|line| {
+            let i = line.find('=')?;
+            let key = line[..i].trim().to_lowercase();
+            if key == "privatekey" || key == "presharedkey" {
+                Some(line[i + 1..].trim().to_string())
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+// WireGuard keys are 32 bytes in padded base64
+#[allow(dead_code)]
+fn check_wireguard_key(name: &str, key: &str) -> Result<(), String> {
+    match base64_decode(key) {
+        Some(bytes) if bytes.len() == 32 && base64_encode(&bytes, false) == key => Ok(()),
+        // do not echo the value, it may well be a private key
+        _ => Err(format!("{} is not a 32 byte base64 key", name)),
+    }
+}
+
+/// The fields of an EMVCo merchant-presented payment code.
+#[derive(Debug, Default)]
+pub struct Emv<'a> {
+    pub dynamic: bool,
+    pub mcc: &'a str,
+    pub currency: &'a str,
+    pub amount: Option<&'a str>,
+    pub country: &'a str,
+    pub name: &'a str,
+    pub city: &'a str,
+    pub postcode: Option<&'a str>,
+    // raw fields as ("ID", value) or ("ID.SUB", value) for templates
+    pub tags: Vec<(&'a str, &'a str)>,
+}
+
+#[derive(Debug)]
+enum EmvTag {
+    Value(String),
+    Template(BTreeMap<u8, String>),
+}
+
+#[allow(dead_code)]
+pub fn emv_string(emv: &Emv) -> Result<String, String> {
+    lazy_static! {
+        static ref AMOUNT: Regex = Regex::new(r"\A[0-9]{1,10}(\.[0-9]{1,2})?\z").unwrap();
+    }
+    if !emv.mcc.chars().all(|c| c.is_ascii_digit()) || emv.mcc.len() != 4 {
+        return Err(format!(
+            "{:?} is not a 4 digit merchant category code",
+            emv.mcc
+        ));
+    }
+    if !emv.currency.chars().all(|c| c.is_ascii_digit()) || emv.currency.len() != 3 {
+        return Err(format!(
+            "{:?} is not a numeric ISO 4217 currency",
+            emv.currency
+        ));
+    }
+    if !emv.country.chars().all(|c| c.is_ascii_alphabetic()) || emv.country.len() != 2 {
+        return Err(format!(
+            "{:?} is not a two letter country code",
+            emv.country
+        ));
+    }
+    if let Some(a) = emv.amount {
+        if !AMOUNT.is_match(a) {
+            return Err(format!("{:?} is not a valid amount", a));
+        }
+    }
+
+    let mut tags = vec![
+        ("01", if emv.dynamic { "12" } else { "11" }),
+        ("52", emv.mcc),
+        ("53", emv.currency),
+        ("58", emv.country),
+        ("59", check_length("merchant name", emv.name, 25)?),
+        ("60", check_length("merchant city", emv.city, 15)?),
+    ];
+    if let Some(a) = emv.amount {
+        tags.push(("54", a));
+    }
+    if let Some(p) = emv.postcode {
+        tags.push(("61", p));
+    }
+    tags.extend(emv.tags.iter().cloned());
+
+    let mut fields: BTreeMap<u8, EmvTag> = BTreeMap::new();
+    for (id, value) in tags {
+        let uppercase;
+        let value = if id == "58" {
+            uppercase = value.to_uppercase();
+            uppercase.as_str()
+        } else {
+            value
+        };
+        emv_insert(&mut fields, id, value)?;
+    }
+    if !fields.keys().any(|id| (2..=51).contains(id)) {
+        return Err("at least one merchant account (tags 02 to 51) is needed".to_string());
+    }
+
+    let mut code = emv_tlv(0, "01")?;
+    for (id, tag) in fields.iter() {
+        code.push_str(&match tag {
+            EmvTag::Value(v) => emv_tlv(*id, v)?,
+            EmvTag::Template(subs) => {
+                let mut inner = String::new();
+                for (sub, v) in subs.iter() {
+                    inner.push_str(&emv_tlv(*sub, v)?);
+                }
+                emv_tlv(*id, &inner)?
+            }
+        });
+    }
+    code.push_str("6304");
+    Ok(format!("{}{:04X}", code, crc16_ccitt(code.as_bytes())))
+}
+
+// builds the brazilian PIX preset on top of the general EMV fields
+#[allow(dead_code)]
+pub fn pix_string(
+    key: &str,
+    name: &str,
+    city: &str,
+    txid: Option<&str>,
+    amount: Option<&str>,
+    description: Option<&str>,
+) -> Result<String, String> {
+    lazy_static! {
+        static ref TXID: Regex = Regex::new(r"\A[A-Za-z0-9]{1,25}\z").unwrap();
+    }
+    if let Some(t) = txid {
+        if !TXID.is_match(t) {
+            return Err(format!(
+                "the txid {:?} may only be 25 letters and digits",
+                t
+            ));
+        }
+    }
+    if key.len() > 77 {
+        return Err("the PIX key can be at most 77 characters long".to_string());
+    }
+
+    let mut tags = vec![("26.00", "br.gov.bcb.pix"), ("26.01", key)];
+    if let Some(d) = description {
+        tags.push(("26.02", d));
+    }
+    // "***" marks a static code without a transaction id
+    tags.push(("62.05", txid.unwrap_or("***")));
+
+    emv_string(&Emv {
+        dynamic: false,
+        mcc: "0000",
+        currency: "986",
+        amount,
+        country: "BR",
+        name,
+        city,
+        postcode: None,
+        tags,
+    })
+}
+
+// adds a raw "ID" or "ID.SUB" field, refusing duplicates and the reserved tags 00 and 63
+#[allow(dead_code)]
+fn emv_insert(fields: &mut BTreeMap<u8, EmvTag>, id: &str, value: &str) -> Result<(), String> {
+    let parse = |part: &str| match part.parse::<u8>() {
+        Ok(n) if n <= 99 && part.len() == 2 => Ok(n),
+        _ => Err(format!("{:?} is not a two digit tag id", id)),
+    };
+    let mut parts = id.splitn(2, '.');
+    let tag = parse(parts.next().unwrap())?;
+    if tag == 0 || tag == 63 {
+        return Err(format!("the tag {} is filled in automatically", id));
+    }
+
+    match (parts.next(), fields.get_mut(&tag)) {
+        (None, None) => {
+            fields.insert(tag, EmvTag::Value(value.to_string()));
+        }
+        (Some(sub), None) => {
+            let mut subs = BTreeMap::new();
+            subs.insert(parse(sub)?, value.to_string());
+            fields.insert(tag, EmvTag::Template(subs));
+        }
+        (Some(sub), Some(EmvTag::Template(subs))) => {
+            if subs.insert(parse(sub)?, value.to_string()).is_some() {
+                return Err(format!("the tag {} is given twice", id));
+            }
+        }
+        _ => return Err(format!("the tag {} is given twice", id)),
+    }
+    Ok(())
+}
+
+#[allow(dead_code)]
+fn emv_tlv(id: u8, value: &str) -> Result<String, String> {
+    if !value.bytes().all(|b| (0x20..=0x7e).contains(&b)) {
+        return Err(format!("{:?} may only contain printable ascii", value));
+    }
+    if value.is_empty() || value.len() > 99 {
+        return Err(format!(
+            "the tag {:02} has to be 1 to 99 characters long",
+            id
+        ));
+    }
+    Ok(format!("{:02}{:02}{}", id, value.len(), value))
+}
+
+// CRC16-CCITT with the polynomial 0x1021 and 0xFFFF as start value
+#[allow(dead_code)]
+fn crc16_ccitt(data: &[u8]) -> u16 {
+    data.iter().fold(0xffff, |crc, &b| {
+        (0..8).fold(crc ^ (u16::from(b) << 8), |crc, _| {
+            if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+// the OpenKeychain format for exchanging OpenPGP fingerprints
+#[allow(dead_code)]
+pub fn openpgp_string(fingerprint: &str) -> Result<String, String> {
+    let hex = fingerprint
+        .trim_start_matches("0x")
+        .replace(" ", "")
+        .to_uppercase();
+    if hex.len() != 40 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("{:?} is not a v4 OpenPGP fingerprint", fingerprint));
+    }
+    Ok(format!("OPENPGP4FPR:{}", hex))
+}
+
+// computes the v4 fingerprint of the primary key in an ASCII-armored public key
+#[allow(dead_code)]
+pub fn openpgp_fingerprint(armored: &str) -> Result<String, String> {
+    let mut lines = armored.lines().map(str::trim);
+    if !lines.any(|l| l == "-----BEGIN PGP PUBLIC KEY BLOCK-----") {
+        return Err("the file is not an ASCII-armored public key".to_string());
+    }
+    // armor headers like "Comment: ..." end at the first blank line
+    let body = lines
+        .skip_while(|l| l.contains(": "))
+        .take_while(|l| !l.starts_with("-----"))
+        .filter(|l| !l.starts_with('='))
+        .collect::<String>();
+    let data =
+        base64_decode(&body).ok_or_else(|| "the armored key is not valid base64".to_string())?;
+
+    let packet =
+        openpgp_first_packet(&data).ok_or_else(|| "the key data is truncated".to_string())?;
+    match packet {
+        (6, [4, ..]) => {}
+        (6, [v, ..]) => {
+            return Err(format!(
+                "only v4 keys have OPENPGP4FPR fingerprints, this is a v{} key",
+                v
+            ))
+        }
+        _ => return Err("the key does not start with a public key packet".to_string()),
+    }
+    let body = packet.1;
+
+    let mut hasher = Sha1::new();
+    hasher.update([0x99]);
+    hasher.update((body.len() as u16).to_be_bytes());
+    hasher.update(body);
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect())
+}
+
+// returns the tag and body of the first packet, in either the old or the new format
+#[allow(dead_code)]
+fn openpgp_first_packet(data: &[u8]) -> Option<(u8, &[u8])> {
+    let header = *data.first()?;
+    if header & 0x80 == 0 {
+        return None;
+    }
+    let (tag, length, offset) = if header & 0x40 == 0 {
+        let size = [1, 2, 4, 0][(header & 3) as usize];
+        if size == 0 {
+            return None;
+        }
+        let length = data
+            .get(1..1 + size)?
+            .iter()
+            .fold(0usize, |n, &b| (n << 8) | usize::from(b));
+        ((header >> 2) & 0xf, length, 1 + size)
+    } else {
+        match *data.get(1)? {
+            o if o < 192 => (header & 0x3f, usize::from(o), 2),
+            o if o < 224 => {
+                let length = (usize::from(o - 192) << 8) + usize::from(*data.get(2)?) + 192;
+                (header & 0x3f, length, 3)
+            }
+            255 => {
+                let length = data
+                    .get(2..6)?
+                    .iter()
+                    .fold(0usize, |n, &b| (n << 8) | usize::from(b));
+                (header & 0x3f, length, 6)
+            }
+            _ => return None,
+        }
+    };
+    Some((tag, data.get(offset..offset + length)?))
+}
+
+// an OpenSSH public key line as its SHA256 fingerprint or as the bare key
+#[allow(dead_code)]
+pub fn ssh_string(key: &str, full: bool) -> Result<String, String> {
+    let line = key
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty() && !l.starts_with('#'))
+        .ok_or_else(|| "there is no public key in the input".to_string())?;
+    let mut parts = line.split_whitespace();
+    let (kind, encoded) = match (parts.next(), parts.next()) {
+        (Some(k), Some(e)) => (k, e),
+        _ => return Err(format!("{:?} is not an OpenSSH public key", line)),
+    };
+
+    // the key blob starts with its own type as a length prefixed string
+    let blob = base64_decode(encoded).ok_or_else(|| "the key is not valid base64".to_string())?;
+    let inner = blob
+        .get(..4)
+        .map(|l| u32::from_be_bytes([l[0], l[1], l[2], l[3]]) as usize)
+        .and_then(|l| blob.get(4..4 + l));
+    if inner != Some(kind.as_bytes()) {
+        return Err(format!(
+            "the key data does not match the key type {:?}",
+            kind
+        ));
+    }
+
+    if full {
+        // the comment often names the user or host and is left out
+        Ok(format!("{} {}", kind, encoded))
+    } else {
+        // like ssh-keygen: the standard alphabet, but without padding
+        let digest = base64_encode(&Sha256::digest(&blob), false);
+        Ok(format!("SHA256:{}", digest.trim_end_matches('=')))
+    }
+}
+
+// builds a magnet link from a .torrent file, leaving out trackers until it fits
+// into max_version; returns the link and the number of trackers left out
+#[allow(dead_code)]
+pub fn magnet_string(
+    torrent: &[u8],
+    max_version: i16,
+    error: EcLevel,
+) -> Result<(String, usize), String> {
+    let root = bencode_decode(torrent)?;
+    let (info, info_raw) = root
+        .get_raw("info")
+        .ok_or_else(|| "the torrent has no info dictionary".to_string())?;
+
+    let hex = |digest: &[u8]| {
+        digest
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()
+    };
+    let is_v2 = matches!(info.get("meta version"), Some(Bencode::Int(2)));
+    let is_v1 = info.get("pieces").is_some();
+    let mut params = Vec::new();
+    if is_v1 || !is_v2 {
+        params.push(format!("xt=urn:btih:{}", hex(&Sha1::digest(info_raw))));
+    }
+    if is_v2 {
+        // a sha2-256 multihash
+        params.push(format!(
+            "xt=urn:btmh:1220{}",
+            hex(&Sha256::digest(info_raw))
+        ));
+    }
+
+    if let Some(Bencode::Bytes(name)) = info.get("name") {
+        params.push(format!("dn={}", uri_escape(&String::from_utf8_lossy(name))));
+    }
+    let length = match (info.get("length"), info.get("files"), info.get("file tree")) {
+        (Some(Bencode::Int(l)), _, _) => Some(*l),
+        (_, Some(Bencode::List(files)), _) => Some(
+            files
+                .iter()
+                .filter_map(|f| match f.get("length") {
+                    Some(Bencode::Int(l)) => Some(*l),
+                    _ => None,
+                })
+                .sum(),
+        ),
+        (_, _, Some(tree)) => Some(torrent_tree_length(tree)),
+        _ => None,
+    };
+    if let Some(l) = length {
+        params.push(format!("xl={}", l));
+    }
+
+    // the announce-list repeats the announce url in most torrents
+    let mut trackers: Vec<&[u8]> = Vec::new();
+    if let Some(Bencode::Bytes(url)) = root.get("announce") {
+        trackers.push(url);
+    }
+    if let Some(Bencode::List(tiers)) = root.get("announce-list") {
+        for tier in tiers {
+            if let Bencode::List(urls) = tier {
+                for url in urls {
+                    if let Bencode::Bytes(u) = url {
+                        if !trackers.contains(u) {
+                            trackers.push(u);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    let trackers = trackers
+        .iter()
+        .map(|t| format!("tr={}", uri_escape(&String::from_utf8_lossy(t))))
+        .collect::<Vec<_>>();
+
+    let fits = |link: &str| QrCode::with_version(link, Version::Normal(max_version), error).is_ok();
+    for kept in (0..=trackers.len()).rev() {
+        let mut all = params.clone();
+        all.extend_from_slice(&trackers[..kept]);
+        let link = format!("magnet:?{}", all.join("&"));
+        if fits(&link) {
+            return Ok((link, trackers.len() - kept));
+        }
+    }
+    Err(format!(
+        "the magnet link does not fit into version {} even without trackers",
+        max_version
+    ))
+}
+
+// v2 torrents nest their files in a tree with the lengths in the leaves
+#[allow(dead_code)]
+fn torrent_tree_length(tree: &Bencode) -> i64 {
+    match tree {
+        Bencode::Dict(entries) => entries
+            .iter()
+            .map(
+                |(key, value, _)| match (key.is_empty(), value.get("length")) {
+                    (true, Some(Bencode::Int(l))) => *l,
+                    _ => torrent_tree_length(value),
+                },
+            )
+            .sum(),
+        _ => 0,
+    }
+}
+
+// the value format of a GS1 Application Identifier
+#[derive(Debug)]
+struct Gs1Format {
+    numeric: bool,
+    min: usize,
+    max: usize,
+    check_digit: bool,
+    date: bool,
+}
+
+// builds a GS1 Digital Link URI or, for the FNC1 mode, an element string
+// with GS as the separator after variable length values
+#[allow(dead_code)]
+pub fn gs1_string(
+    ais: &[(&str, &str)],
+    digital_link: bool,
+    domain: &str,
+) -> Result<String, String> {
+    if ais.is_empty() {
+        return Err("at least one Application Identifier is needed".to_string());
+    }
+    let mut values = Vec::new();
+    for (ai, value) in ais {
+        if values.iter().any(|(a, _)| a == ai) {
+            return Err(format!("the AI ({}) is given twice", ai));
+        }
+        values.push((*ai, gs1_check(ai, value)?));
+    }
+
+    if !digital_link {
+        // the predefined length AIs need no separator, so they go first
+        values.sort_by_key(|(ai, _)| !gs1_is_predefined(ai));
+        let mut element = String::new();
+        for (i, (ai, value)) in values.iter().enumerate() {
+            element.push_str(ai);
+            element.push_str(value);
+            if !gs1_is_predefined(ai) && i + 1 < values.len() {
+                element.push('\u{1d}');
+            }
+        }
+        return Ok(element);
+    }
+
+    // the primary key and its qualifiers form the path, everything else the query
+    let (primary, qualifiers): (&str, &[&str]) = if values.iter().any(|(ai, _)| *ai == "01") {
+        ("01", &["22", "10", "21"])
+    } else if values.iter().any(|(ai, _)| *ai == "00") {
+        ("00", &[])
+    } else if values.iter().any(|(ai, _)| *ai == "414") {
+        ("414", &["254"])
+    } else {
+        return Err("a Digital Link needs a GTIN (01), SSCC (00) or GLN (414)".to_string());
+    };
+    let value_of = |ai: &str| values.iter().find(|(a, _)| *a == ai).map(|(_, v)| v);
+
+    let mut link = domain.trim_end_matches('/').to_string();
+    for ai in std::iter::once(&primary).chain(qualifiers.iter()) {
+        if let Some(v) = value_of(ai) {
+            link.push_str(&format!("/{}/{}", ai, uri_escape(v)));
+        }
+    }
+    let query = values
+        .iter()
+        .filter(|(ai, _)| *ai != primary && !qualifiers.contains(ai))
+        .map(|(ai, v)| format!("{}={}", ai, uri_escape(v)))
+        .collect::<Vec<_>>();
+    if !query.is_empty() {
+        link.push('?');
+        link.push_str(&query.join("&"));
+    }
+    Ok(link)
+}
+
+// validates a value against its AI and returns it, GTINs padded to 14 digits
+#[allow(dead_code)]
+fn gs1_check(ai: &str, value: &str) -> Result<String, String> {
+    let format = gs1_format(ai).ok_or_else(|| format!("the AI ({}) is not supported", ai))?;
+    let value = if ai == "01" || ai == "02" {
+        format!("{:0>14}", value)
+    } else {
+        value.to_string()
+    };
+
+    // the 82 characters GS1 allows in alphanumeric values
+    let is_allowed = |c: char| c.is_ascii_alphanumeric() || "!\"%&'()*+,-./:;<=>?_".contains(c);
+    let is_valid = value.len() >= format.min
+        && value.len() <= format.max
+        && if format.numeric {
+            value.chars().all(|c| c.is_ascii_digit())
+        } else {
+            value.chars().all(is_allowed)
+        };
+    if !is_valid {
+        let kind = if format.numeric {
+            "digits"
+        } else {
+            "characters"
+        };
+        return Err(if format.min == format.max {
+            format!("the AI ({}) needs exactly {} {}", ai, format.max, kind)
+        } else {
+            format!(
+                "the AI ({}) needs {} to {} {}",
+                ai, format.min, format.max, kind
+            )
+        });
+    }
+
+    if format.check_digit {
+        let (body, check) = value.split_at(value.len() - 1);
+        if gs1_check_digit(body).to_string() != check {
+            return Err(format!(
+                "the check digit of {:?} should be {}",
+                value,
+                gs1_check_digit(body)
+            ));
+        }
+    }
+    if format.date {
+        // the day may be 00 for "end of the month"
+        let month = value[2..4].parse::<u32>().unwrap();
+        let day = value[4..6].parse::<u32>().unwrap();
+        if !(1..=12).contains(&month) || day > 31 {
+            return Err(format!("the AI ({}) needs a date like YYMMDD", ai));
+        }
+    }
+    Ok(value)
+}
+
+#[allow(dead_code)]
+fn gs1_format(ai: &str) -> Option<Gs1Format> {
+    let f = |numeric, min, max| Gs1Format {
+        numeric,
+        min,
+        max,
+        check_digit: false,
+        date: false,
+    };
+    let format = match ai {
+        "00" => Gs1Format {
+            check_digit: true,
+            ..f(true, 18, 18)
+        },
+        "01" | "02" => Gs1Format {
+            check_digit: true,
+            ..f(true, 14, 14)
+        },
+        "410" | "411" | "412" | "413" | "414" | "415" | "416" | "417" => Gs1Format {
+            check_digit: true,
+            ..f(true, 13, 13)
+        },
+        "11" | "12" | "13" | "15" | "16" | "17" => Gs1Format {
+            date: true,
+            ..f(true, 6, 6)
+        },
+        "10" | "21" | "22" => f(false, 1, 20),
+        "20" => f(true, 2, 2),
+        "30" | "37" => f(true, 1, 8),
+        "240" | "241" | "250" | "251" | "400" | "401" | "403" | "90" => f(false, 1, 30),
+        "254" => f(false, 1, 20),
+        "422" | "424" | "426" => f(true, 3, 3),
+        "8200" => f(false, 1, 70),
+        "91" | "92" | "93" | "94" | "95" | "96" | "97" | "98" | "99" => f(false, 1, 90),
+        // measures like 3103 (net weight in kg with three decimals)
+        _ if ai.len() == 4
+            && ai.chars().all(|c| c.is_ascii_digit())
+            && ("31".."37").contains(&&ai[..2]) =>
+        {
+            f(true, 6, 6)
+        }
+        _ => return None,
+    };
+    Some(format)
+}
+
+// AIs whose total length is fixed by their first two digits never need a separator
+#[allow(dead_code)]
+fn gs1_is_predefined(ai: &str) -> bool {
+    matches!(
+        &ai[..2],
+        "00" | "01"
+            | "02"
+            | "03"
+            | "04"
+            | "11"
+            | "12"
+            | "13"
+            | "14"
+            | "15"
+            | "16"
+            | "17"
+            | "18"
+            | "19"
+            | "20"
+            | "31"
+            | "32"
+            | "33"
+            | "34"
+            | "35"
+            | "36"
+            | "41"
+    )
+}
+
+// the GS1 mod 10 check digit, weighting the digits 3 and 1 from the right
+#[allow(dead_code)]
+fn gs1_check_digit(digits: &str) -> u32 {
+    let sum: u32 = digits
+        .chars()
+        .rev()
+        .enumerate()
+        .map(|(i, c)| c.to_digit(10).unwrap() * if i % 2 == 0 { 3 } else { 1 })
+        .sum();
+    (10 - sum % 10) % 10
+}
+
+#[allow(dead_code)]
+pub fn url_string(url: &str) -> String {
+    if !url.starts_with("http") {
+        "http://".to_string() + url
+    } else {
+        String::from(url)
+    }
+}
+
+#[allow(dead_code)]
+fn escape_input(inp: &str, simple: bool) -> String {
+    let mut forbidden = Vec::new();
+    if simple {
+        forbidden.push(":");
+    } else {
+        forbidden.push("\\");
+        forbidden.push(";");
+        forbidden.push(",");
+        forbidden.push(":");
+    };
+
+    let mut n = String::from(inp);
+    for c in &forbidden {
+        n = str::replace(&n, c, &("\\".to_string() + c));
+    }
+    n
+}
+
+#[allow(dead_code)]
+fn uri_escape(inp: &str) -> String {
+    quote(inp, b"").ok().unwrap()
+}
+
+// the wifi format escapes its separators, the quote and the backslash itself
+#[allow(dead_code)]
+fn escape_wifi(inp: &str) -> String {
+    let mut n = String::with_capacity(inp.len());
+    for c in inp.chars() {
+        if let '\\' | ';' | ',' | ':' | '"' = c {
+            n.push('\\');
+        }
+        n.push(c);
+    }
+    n
+}
+
+#[allow(dead_code)]
+fn is_hexstyle(arg: &str) -> bool {
+    lazy_static! {
+        static ref HEX: Regex = Regex::new(r"\A[0-9a-fA-F]+\z").unwrap();
+    }
+
+    HEX.is_match(arg)
+}
+
+// strips the spaces from an IBAN and checks its country code and mod-97 checksum
+#[allow(dead_code)]
+fn normalize_iban(iban: &str) -> Result<String, String> {
+    let n = iban.replace(" ", "").to_uppercase();
+    lazy_static! {
+        static ref IBAN: Regex = Regex::new(r"\A[A-Z]{2}[0-9]{2}[A-Z0-9]{11,30}\z").unwrap();
+    }
+    if !IBAN.is_match(&n) {
+        return Err(format!("{:?} is not a well-formed IBAN", iban));
+    }
+
+    if mod97(&n) != 1 {
+        return Err(format!("the IBAN {:?} has an invalid checksum", iban));
+    }
+    Ok(n)
+}
+
+// ISO 7064 mod 97-10 as used by IBANs and ISO 11649 creditor references
+#[allow(dead_code)]
+fn mod97(inp: &str) -> u32 {
+    // move the country code and check digits to the back and read letters as 10..35
+    let rearranged = inp[4..].to_string() + &inp[..4];
+    rearranged.chars().fold(0u32, |acc, c| {
+        let v = c.to_digit(36).unwrap();
+        if v < 10 {
+            (acc * 10 + v) % 97
+        } else {
+            (acc * 100 + v) % 97
+        }
+    })
+}
+
+// the Verhoeff check digit, which catches all single digit errors and transpositions
+#[allow(dead_code)]
+fn verhoeff(digits: &str) -> u8 {
+    const D: [[u8; 10]; 10] = [
+        [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+        [1, 2, 3, 4, 0, 6, 7, 8, 9, 5],
+        [2, 3, 4, 0, 1, 7, 8, 9, 5, 6],
+        [3, 4, 0, 1, 2, 8, 9, 5, 6, 7],
+        [4, 0, 1, 2, 3, 9, 5, 6, 7, 8],
+        [5, 9, 8, 7, 6, 0, 4, 3, 2, 1],
+        [6, 5, 9, 8, 7, 1, 0, 4, 3, 2],
+        [7, 6, 5, 9, 8, 2, 1, 0, 4, 3],
+        [8, 7, 6, 5, 9, 3, 2, 1, 0, 4],
+        [9, 8, 7, 6, 5, 4, 3, 2, 1, 0],
+    ];
+    const P: [[u8; 10]; 8] = [
+        [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+        [1, 5, 7, 6, 2, 8, 3, 0, 9, 4],
+        [5, 8, 0, 3, 7, 9, 6, 1, 4, 2],
+        [8, 9, 1, 6, 0, 4, 3, 5, 2, 7],
+        [9, 4, 5, 3, 1, 2, 6, 8, 7, 0],
+        [4, 2, 8, 6, 5, 7, 3, 9, 0, 1],
+        [2, 7, 9, 3, 8, 0, 6, 4, 1, 5],
+        [7, 0, 4, 6, 9, 1, 3, 2, 5, 8],
+    ];
+    const INV: [u8; 10] = [0, 4, 3, 2, 1, 5, 6, 7, 8, 9];
+    let check = digits.bytes().rev().enumerate().fold(0, |c, (i, b)| {
+        D[c as usize][P[(i + 1) % 8][(b - b'0') as usize] as usize]
+    });
+    INV[check as usize]
+}
+
+// the recursive mod 10 check digit used by QR references
+#[allow(dead_code)]
+fn mod10_recursive(digits: &str) -> u32 {
+    const TABLE: [u32; 10] = [0, 9, 4, 6, 8, 2, 7, 1, 3, 5];
+    let carry = digits.chars().fold(0, |carry, c| {
+        TABLE[((carry + c.to_digit(10).unwrap()) % 10) as usize]
+    });
+    (10 - carry) % 10
+}
+
+#[allow(dead_code)]
+fn normalize_bic(bic: &str) -> Result<String, String> {
+    let n = bic.replace(" ", "").to_uppercase();
+    lazy_static! {
+        static ref BIC: Regex = Regex::new(r"\A[A-Z]{6}[A-Z0-9]{2}([A-Z0-9]{3})?\z").unwrap();
+    }
+    if !BIC.is_match(&n) {
+        return Err(format!("{:?} is not a valid BIC", bic));
+    }
+    Ok(n)
+}
+
+// parses a decimal amount like "12.5" into cents without going through floats
+#[allow(dead_code)]
+fn parse_cents(amount: &str) -> Result<u64, String> {
+    let err = || format!("{:?} is not a valid amount", amount);
+    let (whole, frac) = match amount.find('.') {
+        Some(i) => (&amount[..i], &amount[i + 1..]),
+        None => (amount, ""),
+    };
+    let digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    if whole.is_empty() || whole.len() > 15 || frac.len() > 2 || !digits(whole) || !digits(frac) {
+        return Err(err());
+    }
+    let frac = format!("{:0<2}", frac);
+    Ok(whole.parse::<u64>().map_err(|_| err())? * 100 + frac.parse::<u64>().map_err(|_| err())?)
+}
+
+#[allow(dead_code)]
+fn check_length<'a>(field: &str, value: &'a str, max: usize) -> Result<&'a str, String> {
+    if value.chars().count() > max {
+        return Err(format!(
+            "the {} can be at most {} characters long",
+            field, max
+        ));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wifi(ssid: &str, password: &str, mode: &Authentication, is_hidden: bool) -> String {
+        wifi_string(ssid, password, mode, is_hidden, &WifiOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn wifi_tricky_ssids() {
+        let table = [
+            ("Home", r#"WIFI:T:WPA;S:Home;P:secret123;;"#),
+            ("CAFE", r#"WIFI:T:WPA;S:"CAFE";P:secret123;;"#),
+            ("deadbeef01", r#"WIFI:T:WPA;S:"deadbeef01";P:secret123;;"#),
+            ("0xCAFE", r#"WIFI:T:WPA;S:0xCAFE;P:secret123;;"#),
+            ("Cafe Bar", r#"WIFI:T:WPA;S:Cafe Bar;P:secret123;;"#),
+            ("a;b", r#"WIFI:T:WPA;S:a\;b;P:secret123;;"#),
+            ("a,b:c", r#"WIFI:T:WPA;S:a\,b\:c;P:secret123;;"#),
+            (r#"say "hi""#, r#"WIFI:T:WPA;S:say \"hi\";P:secret123;;"#),
+            (r"back\slash", r"WIFI:T:WPA;S:back\\slash;P:secret123;;"),
+            (r#""quoted""#, r#"WIFI:T:WPA;S:\"quoted\";P:secret123;;"#),
+            ("Café ☕", "WIFI:T:WPA;S:Café ☕;P:secret123;;"),
+            (
+                "WIFI:T:WEP;",
+                r#"WIFI:T:WPA;S:WIFI\:T\:WEP\;;P:secret123;;"#,
+            ),
+        ];
+        for (ssid, expected) in table.iter() {
+            assert_eq!(
+                wifi(ssid, "secret123", &Authentication::WPA, false),
+                *expected,
+                "ssid {:?}",
+                ssid
+            );
+        }
+    }
+
+    #[test]
+    fn wifi_passwords_are_escaped_but_never_quoted() {
+        let psk = "a".repeat(64);
+        assert_eq!(
+            wifi("Home", &psk, &Authentication::WPA, false),
+            format!("WIFI:T:WPA;S:Home;P:{};;", psk)
+        );
+        assert_eq!(
+            wifi("Home", "CAFE1234", &Authentication::WPA, false),
+            "WIFI:T:WPA;S:Home;P:CAFE1234;;"
+        );
+        assert_eq!(
+            wifi("Home", r#"p;a"s\s"#, &Authentication::WPA, false),
+            r#"WIFI:T:WPA;S:Home;P:p\;a\"s\\s;;"#
+        );
+    }
+
+    #[test]
+    fn wifi_field_order() {
+        assert_eq!(
+            wifi("Home", "secret123", &Authentication::WPA, true),
+            "WIFI:T:WPA;S:Home;P:secret123;H:true;;"
+        );
+        assert_eq!(
+            wifi("Guest", "", &Authentication::nopass, false),
+            "WIFI:T:nopass;S:Guest;;"
+        );
+
+        let options = WifiOptions {
+            eap: Some(WifiEap {
+                method: "peap",
+                identity: "bob@example.org",
+                anonymous_identity: Some("anon"),
+                phase2: Some("mschapv2"),
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            wifi_string("eduroam", "pw", &Authentication::WPA2_EAP, true, &options).unwrap(),
+            "WIFI:T:WPA2-EAP;S:eduroam;P:pw;E:PEAP;A:anon;AI:anon;I:bob@example.org;\
+             PH2:MSCHAPV2;H:true;;"
+        );
+
+        let options = WifiOptions {
+            transition_disable: true,
+            public_key: Some("MDkwEwYHKoZIzj0CAQ=="),
+            ..Default::default()
+        };
+        assert_eq!(
+            wifi_string("Office", "pw", &Authentication::SAE, false, &options).unwrap(),
+            "WIFI:T:SAE;R:1;S:Office;P:pw;K:MDkwEwYHKoZIzj0CAQ==;;"
+        );
+    }
+
+    #[test]
+    fn wifi_rejects_invalid_combinations() {
+        let none = WifiOptions::default();
+        assert!(wifi_string("", "pw", &Authentication::WPA, false, &none).is_err());
+        assert!(wifi_string(&"x".repeat(33), "pw", &Authentication::WPA, false, &none).is_err());
+        assert!(wifi_string("Guest", "pw", &Authentication::nopass, false, &none).is_err());
+        assert!(wifi_string("eduroam", "pw", &Authentication::WPA2_EAP, false, &none).is_err());
+    }
+
+    #[test]
+    fn giro_epc_example() {
+        let giro = Giro {
+            version: "002",
+            charset: "1",
+            bic: Some("BPOTBEB1"),
+            name: "Red Cross of Belgium",
+            iban: "BE72 0000 0000 1616",
+            amount: Some("1"),
+            purpose: Some("char"),
+            text: Some("Urgency fund"),
+            ..Default::default()
+        };
+        assert_eq!(
+            giro_string(&giro).unwrap(),
+            "BCD\n002\n1\nSCT\nBPOTBEB1\nRed Cross of Belgium\nBE72000000001616\nEUR1.00\nCHAR\n\n\
+             Urgency fund"
+        );
+
+        let version_1 = Giro {
+            version: "001",
+            bic: None,
+            ..giro
+        };
+        assert!(giro_string(&version_1).is_err());
+    }
+
+    #[test]
+    fn iban_checksums() {
+        for iban in [
+            "DE89 3704 0044 0532 0130 00",
+            "GB82WEST12345698765432",
+            "BE72000000001616",
+        ] {
+            assert!(normalize_iban(iban).is_ok(), "iban {:?}", iban);
+        }
+        assert_eq!(
+            normalize_iban("de89 3704 0044 0532 0130 00").unwrap(),
+            "DE89370400440532013000"
+        );
+        for iban in [
+            "DE89370400440532013001",
+            "GB28WEST12345698765432",
+            "DE8937040044",
+            "1234",
+        ] {
+            assert!(normalize_iban(iban).is_err(), "iban {:?}", iban);
+        }
+        // ISO 11649 creditor references share the mod 97-10 check
+        assert_eq!(mod97("RF18539007547034"), 1);
+        assert_ne!(mod97("RF19539007547034"), 1);
+    }
+
+    #[test]
+    fn bic_format() {
+        assert_eq!(normalize_bic("deut de ff").unwrap(), "DEUTDEFF");
+        assert_eq!(normalize_bic("DEUTDEFF500").unwrap(), "DEUTDEFF500");
+        for bic in ["DEUTDEF", "DEUT1EFF", "DEUTDEFF50", "DEUTDEFF5000"] {
+            assert!(normalize_bic(bic).is_err(), "bic {:?}", bic);
+        }
+    }
+
+    #[test]
+    fn eip55_vectors() {
+        // the test vectors of EIP-55
+        let table = [
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+            "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+        ];
+        for address in table.iter() {
+            assert_eq!(eip55_checksum(address).unwrap(), *address);
+            assert_eq!(eip55_checksum(&address.to_lowercase()).unwrap(), *address);
+        }
+        assert_eq!(
+            eip55_checksum("0x5AAEB6053F3E94C9B9A09F33669435E7EF1BEAED").unwrap(),
+            table[0]
+        );
+
+        // a single flipped case breaks the checksum
+        assert!(eip55_checksum("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD").is_err());
+        assert!(eip55_checksum("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeA").is_err());
+        assert!(eip55_checksum("5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed").is_err());
+    }
+
+    #[test]
+    fn lightning_fallback_addresses() {
+        // only the prefix of an invoice matters here, so any valid bech32 string will do
+        let invoice = |hrp: &str| bech32_encode(hrp, &[0; 10], &Bech32Variant::Bech32);
+        let mainnet = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+        let testnet = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+
+        assert_eq!(
+            lightning_string(&invoice("lnbc"), Some(mainnet)).unwrap(),
+            format!(
+                "bitcoin:{}?lightning={}",
+                mainnet.to_uppercase(),
+                invoice("lnbc").to_uppercase()
+            )
+        );
+        assert!(lightning_string(&invoice("lntb"), Some(testnet)).is_ok());
+        assert!(lightning_string(&invoice("lntbs"), Some(testnet)).is_ok());
+        assert!(lightning_string(&invoice("lnbc"), Some(testnet)).is_err());
+        assert!(lightning_string(&invoice("lntb"), Some(mainnet)).is_err());
+        assert!(lightning_string(
+            &invoice("lnbc"),
+            Some("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5")
+        )
+        .is_err());
+        assert!(lightning_string(&invoice("lnbc"), Some("not an address")).is_err());
+    }
+
+    #[test]
+    fn bitcoin_network_mismatch_names_every_network() {
+        let testnet = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+        let bitcoin = |network| {
+            bitcoin_string(
+                &BitcoinScheme::Bitcoin,
+                testnet,
+                Some(network),
+                None,
+                None,
+                None,
+                &[],
+            )
+        };
+        assert!(bitcoin(&BitcoinNetwork::Signet).is_ok());
+        assert_eq!(
+            bitcoin(&BitcoinNetwork::Mainnet).unwrap_err(),
+            format!(
+                "{:?} is a Testnet or Signet address, not a Mainnet one",
+                testnet
+            )
+        );
+    }
+
+    // a cashaddr of the all zero key hash, with a valid checksum for the prefix
+    fn cashaddr(prefix: &str) -> String {
+        let mut data = convert_bits(&[0; 21], 8, 5, true).unwrap();
+        let mut values: Vec<u8> = prefix.bytes().map(|b| b & 31).collect();
+        values.push(0);
+        values.extend(&data);
+        values.extend(&[0; 8]);
+        let checksum = cashaddr_polymod(&values);
+        data.extend((0..8).map(|i| ((checksum >> (5 * (7 - i))) & 31) as u8));
+        let payload = data
+            .iter()
+            .map(|&d| BECH32_ALPHABET[d as usize] as char)
+            .collect::<String>();
+        format!("{}:{}", prefix, payload)
+    }
+
+    #[test]
+    fn bitcoin_cash_keeps_the_network_prefix() {
+        let bch = |address: &str, network| {
+            bitcoin_string(
+                &BitcoinScheme::BitcoinCash,
+                address,
+                network,
+                None,
+                None,
+                None,
+                &[],
+            )
+        };
+        let mainnet = "bitcoincash:qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6a";
+        assert_eq!(bch(mainnet, None).unwrap(), mainnet);
+        assert_eq!(bch(&mainnet[12..], None).unwrap(), mainnet);
+
+        let testnet = cashaddr("bchtest");
+        assert_eq!(
+            bch(&testnet, Some(&BitcoinNetwork::Testnet)).unwrap(),
+            testnet
+        );
+        assert!(bch(&testnet, Some(&BitcoinNetwork::Mainnet)).is_err());
+        assert_eq!(bch(&cashaddr("bchreg"), None).unwrap(), cashaddr("bchreg"));
+    }
+
+    #[test]
+    fn bitcoin_extra_parameters() {
+        let address = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+        let bitcoin = |label, extra: &[(&str, &str)]| {
+            bitcoin_string(
+                &BitcoinScheme::Bitcoin,
+                address,
+                None,
+                None,
+                label,
+                None,
+                extra,
+            )
+        };
+        assert_eq!(
+            bitcoin(None, &[("label", "x")]).unwrap(),
+            format!("bitcoin:{}?label=x", address)
+        );
+        assert!(bitcoin(Some("y"), &[("label", "x")]).is_err());
+        assert!(bitcoin(None, &[("req-label", "x"), ("label", "y")]).is_err());
+    }
+
+    #[test]
+    fn wifi_generated_passwords() {
+        assert!(wifi_generate_password(7, &PasswordCharset::Alphanumeric).is_err());
+        assert!(wifi_generate_password(64, &PasswordCharset::Alphanumeric).is_err());
+        for &length in [8, 20, 63].iter() {
+            let password = wifi_generate_password(length, &PasswordCharset::Symbols).unwrap();
+            assert_eq!(password.len(), length);
+        }
+
+        let sample = |charset| {
+            (0..20)
+                .map(|_| wifi_generate_password(63, &charset).unwrap())
+                .collect::<String>()
+        };
+        assert!(sample(PasswordCharset::Alphanumeric)
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric()));
+        // readable passwords leave out look-alikes such as 0/O and 1/l/I
+        assert!(!sample(PasswordCharset::Readable).contains(|c| "01OIlo".contains(c)));
+        // symbols never need escaping inside a wifi code
+        let symbols = sample(PasswordCharset::Symbols);
+        assert!(symbols.chars().all(|c| c.is_ascii_graphic()));
+        assert!(!symbols.contains(|c| r#"\;,:""#.contains(c)));
+    }
+
+    #[test]
+    fn matter_spec_examples() {
+        // the test device of the Matter SDK
+        let device = |discriminator, passcode| Matter {
+            vendor_id: 0xfff1,
+            product_id: 0x8000,
+            flow: MatterFlow::Standard,
+            discovery: &[MatterDiscovery::BLE],
+            discriminator,
+            passcode,
+        };
+        let matter = device(3840, 20202021);
+        assert_eq!(matter_string(&matter).unwrap(), "MT:Y.K9042C00KA0648G00");
+        assert_eq!(matter_manual_code(&matter).unwrap(), "34970112332");
+
+        for &passcode in [0, 11111111, 12345678, 99999999].iter() {
+            assert!(matter_string(&device(3840, passcode)).is_err());
+        }
+        assert!(matter_string(&device(0x1000, 20202021)).is_err());
+    }
+
+    #[test]
+    fn base38_vectors() {
+        assert_eq!(base38_encode(&[10]), "A0");
+        assert_eq!(base38_encode(&[10, 10]), "OT10");
+        assert_eq!(base38_encode(&[10, 10, 10]), "-N.B0");
+        assert_eq!(base38_encode(&[10, 10, 10, 10]), "-N.B0A0");
+    }
+
+    #[test]
+    fn verhoeff_check_digits() {
+        assert_eq!(verhoeff("236"), 3);
+        assert_eq!(verhoeff("12345"), 1);
+        assert_eq!(verhoeff("3497011233"), 2);
+        // single digit errors and swapped neighbours change the check digit
+        assert_ne!(verhoeff("3497011234"), 2);
+        assert_ne!(verhoeff("3497011323"), 2);
+    }
+
+    #[test]
+    fn emv_crc16_vectors() {
+        // CRC-16/CCITT-FALSE check value
+        assert_eq!(crc16_ccitt(b"123456789"), 0x29b1);
+        // the static Pix example from the manual of the Banco Central do Brasil
+        let pix = "00020126580014br.gov.bcb.pix0136123e4567-e12b-12d1-a456-426655440000\
+                   5204000053039865802BR5913Fulano de Tal6008BRASILIA62070503***6304";
+        assert_eq!(crc16_ccitt(pix.as_bytes()), 0x1d3d);
+        assert_ne!(crc16_ccitt(pix.replace("Tal", "Tai").as_bytes()), 0x1d3d);
+    }
+
+    #[test]
+    fn wireguard_config_errors_hide_the_line() {
+        let key = "yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=";
+        let errors = [
+            (
+                format!("[Interface]\n# comment\nPrivateKey {}", key),
+                "line 3 has no value",
+            ),
+            (
+                format!("[Interface]\n{}", &key[..43]),
+                "line 2 is not a key = value line",
+            ),
+            (
+                format!("PrivateKey = {}", key),
+                "line 1 is outside of any section",
+            ),
+        ];
+        for (config, error) in errors.iter() {
+            let message = wireguard_config(config).unwrap_err();
+            assert_eq!(message, *error);
+        }
+    }
+
+    #[test]
+    fn swissqr_six_example() {
+        // example 1 of the SIX implementation guidelines
+        let bill = SwissQr {
+            iban: "CH44 3199 9123 0008 8901 2",
+            creditor: SwissAddress {
+                name: "Robert Schneider AG",
+                street: "Rue du Lac",
+                building: "1268",
+                postcode: "2501",
+                town: "Biel",
+                country: "CH",
+            },
+            amount: Some("1949.75"),
+            currency: "CHF",
+            debtor: Some(SwissAddress {
+                name: "Pia-Maria Rutschmann-Schnyder",
+                street: "Grosse Marktgasse",
+                building: "28",
+                postcode: "9400",
+                town: "Rorschach",
+                country: "CH",
+            }),
+            reference: Some("21 00000 00003 13947 14300 09017"),
+            message: Some("Order dated 18.06.2020"),
+            billing: Some("//S1/10/10201409/11/200701/20/140.000-53/30/102673831/31/200615/32/7.7/33/7.7:139.40/40/0:30"),
+        };
+        assert_eq!(
+            swissqr_string(&bill).unwrap(),
+            "SPC\n0200\n1\nCH4431999123000889012\n\
+             S\nRobert Schneider AG\nRue du Lac\n1268\n2501\nBiel\nCH\n\
+             \n\n\n\n\n\n\n\
+             1949.75\nCHF\n\
+             S\nPia-Maria Rutschmann-Schnyder\nGrosse Marktgasse\n28\n9400\nRorschach\nCH\n\
+             QRR\n210000000003139471430009017\nOrder dated 18.06.2020\nEPD\n\
+             //S1/10/10201409/11/200701/20/140.000-53/30/102673831/31/200615/32/7.7/33/7.7:139.40/40/0:30"
+        );
+
+        let wrong_check_digit = SwissQr {
+            reference: Some("210000000003139471430009018"),
+            ..bill
+        };
+        assert!(swissqr_string(&wrong_check_digit).is_err());
+    }
+
+    #[test]
+    fn swiss_references() {
+        assert_eq!(mod10_recursive("21000000000313947143000901"), 7);
+        assert_eq!(mod10_recursive("00000000000000000000000000"), 0);
+        assert_ne!(mod10_recursive("21000000000313947143000910"), 7);
+
+        let bill = |reference| SwissQr {
+            iban: "CH5800791123000889012",
+            creditor: SwissAddress {
+                name: "Robert Schneider AG",
+                postcode: "2501",
+                town: "Biel",
+                country: "CH",
+                ..SwissAddress::default()
+            },
+            currency: "CHF",
+            reference: Some(reference),
+            ..SwissQr::default()
+        };
+        let payload = swissqr_string(&bill("RF18 5390 0754 7034")).unwrap();
+        assert!(payload.contains("\nSCOR\nRF18539007547034\n"));
+        // wrong check digits, a QR reference on a normal IBAN and a malformed reference
+        for reference in ["RF19539007547034", "210000000003139471430009017", "RF1"].iter() {
+            assert!(swissqr_string(&bill(reference)).is_err());
+        }
+    }
+
+    #[test]
+    fn hotp_rfc4226_vectors() {
+        let secret = base32_encode(b"12345678901234567890");
+        let codes = [
+            "755224", "287082", "359152", "969429", "338314", "254676", "287922", "162583",
+            "399871", "520489",
+        ];
+        for (counter, code) in codes.iter().enumerate() {
+            let otp = otp_code(&secret, &OtpAlgorithm::SHA1, 6, counter as u64).unwrap();
+            assert_eq!(otp, *code);
+        }
+    }
+
+    #[test]
+    fn totp_rfc6238_vectors() {
+        let sha1 = base32_encode(b"12345678901234567890");
+        let times = [
+            (59, "94287082"),
+            (1_111_111_109, "07081804"),
+            (1_111_111_111, "14050471"),
+            (1_234_567_890, "89005924"),
+            (2_000_000_000, "69279037"),
+            (20_000_000_000, "65353130"),
+        ];
+        for (time, code) in times.iter() {
+            let otp = otp_code(&sha1, &OtpAlgorithm::SHA1, 8, time / 30).unwrap();
+            assert_eq!(otp, *code);
+        }
+
+        let sha256 = base32_encode(b"12345678901234567890123456789012");
+        let otp = otp_code(&sha256, &OtpAlgorithm::SHA256, 8, 59 / 30).unwrap();
+        assert_eq!(otp, "46119246");
+        let sha512 = base32_encode(&b"1234567890".repeat(7)[..64]);
+        let otp = otp_code(&sha512, &OtpAlgorithm::SHA512, 8, 59 / 30).unwrap();
+        assert_eq!(otp, "90693936");
+    }
+
+    #[test]
+    fn otp_uris() {
+        let uri = otp_string(
+            &OtpKind::Totp { period: 30 },
+            Some("ACME Co"),
+            "john@example.com",
+            "jbsw y3dp ehpk 3pxp",
+            &OtpAlgorithm::SHA1,
+            6,
+        )
+        .unwrap();
+        assert_eq!(
+            uri,
+            "otpauth://totp/ACME%20Co:john%40example.com?secret=JBSWY3DPEHPK3PXP\
+             &issuer=ACME%20Co&algorithm=SHA1&digits=6&period=30"
+        );
+        let hotp = OtpKind::Hotp { counter: 0 };
+        assert!(otp_string(
+            &hotp,
+            None,
+            "a:b",
+            "JBSWY3DPEHPK3PXP",
+            &OtpAlgorithm::SHA1,
+            6
+        )
+        .is_err());
+        assert!(otp_string(
+            &hotp,
+            None,
+            "ab",
+            "JBSWY3DPEHPK3PXP",
+            &OtpAlgorithm::SHA1,
+            9
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn dpp_uris() {
+        // the bootstrapping URI examples of the Wi-Fi Easy Connect specification
+        let key =
+            "MDkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDIgADM2206avxHJaHXgLMkq/24e0rsrfMP9K1Tm8gx+ovP0I=";
+        let dpp = Dpp {
+            channels: Some("81/1,115/36"),
+            mac: None,
+            info: None,
+            version: None,
+            host: None,
+            key,
+        };
+        assert_eq!(
+            dpp_string(&dpp).unwrap(),
+            format!("DPP:C:81/1,115/36;K:{};;", key)
+        );
+        let full = Dpp {
+            mac: Some("52:54:00:58:28:E5"),
+            info: Some("SN=4774LH2b4044"),
+            version: Some("2"),
+            ..dpp
+        };
+        assert_eq!(
+            dpp_string(&full).unwrap(),
+            format!(
+                "DPP:C:81/1,115/36;M:5254005828e5;V:2;I:SN=4774LH2b4044;K:{};;",
+                key
+            )
+        );
+
+        for channels in ["81", "81/0", "81/1,", "300/1"].iter() {
+            assert!(dpp_string(&Dpp {
+                channels: Some(channels),
+                ..dpp
+            })
+            .is_err());
+        }
+        // not base64, an RSA key and a P-256 key whose point is off the curve
+        let rsa = "MFwwDQYJKoZIhvcNAQEBBQADSwAwSAJBAKj34GkxFhD90vcNLYLInFEX6Ppy1tPf9Cnzj4p4WGeKLs1Pt8QuKUpRKfFLfRYC9AIKjbJTWit+CqvjWYzvQwECAwEAAQ==";
+        let off_curve = key.replace("M2206", "M2207");
+        for bad in ["not a key", rsa, off_curve.as_str()].iter() {
+            assert!(dpp_check_key(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn esim_activation_codes() {
+        // the activation code examples of GSMA SGP.22
+        let id = "04386-AGYFT-A74Y8-3F815";
+        assert_eq!(
+            esim_string("smdp.gsma.com", id, None, false).unwrap(),
+            "LPA:1$SMDP.GSMA.COM$04386-AGYFT-A74Y8-3F815"
+        );
+        assert_eq!(
+            esim_string("SMDP.GSMA.COM", id, None, true).unwrap(),
+            "LPA:1$SMDP.GSMA.COM$04386-AGYFT-A74Y8-3F815$$1"
+        );
+        assert_eq!(
+            esim_string("SMDP.GSMA.COM", id, Some("1.3.6.1.4.1.31746"), true).unwrap(),
+            "LPA:1$SMDP.GSMA.COM$04386-AGYFT-A74Y8-3F815$1.3.6.1.4.1.31746$1"
+        );
+        assert_eq!(
+            esim_string("SMDP.GSMA.COM", id, Some("1.3.6.1.4.1.31746"), false).unwrap(),
+            "LPA:1$SMDP.GSMA.COM$04386-AGYFT-A74Y8-3F815$1.3.6.1.4.1.31746"
+        );
+
+        for smdp in [
+            "",
+            "smdp",
+            "smdp.gsma.com:443",
+            "smdp$gsma.com",
+            "-smdp.gsma.com",
+        ]
+        .iter()
+        {
+            assert!(esim_string(smdp, id, None, false).is_err(), "{}", smdp);
+        }
+        assert!(esim_string("smdp.gsma.com", "04386$1", None, false).is_err());
+        assert!(esim_string("smdp.gsma.com", id, Some("1.03"), false).is_err());
+    }
+
+    #[test]
+    fn spayd_example() {
+        // the example of the czech banking association, with a due date added
+        let payment = BankPayment {
+            iban: "CZ58 5500 0000 0012 6509 8001",
+            amount: Some("480.5"),
+            currency: "czk",
+            due_date: Some("2024-02-29"),
+            message: Some("Platba za elektrinu"),
+            variable_symbol: Some("1234567890"),
+            ..BankPayment::default()
+        };
+        assert_eq!(
+            spayd_string(&payment).unwrap(),
+            "SPD*1.0*ACC:CZ5855000000001265098001*AM:480.50*CC:CZK*DT:20240229\
+             *MSG:Platba za elektrinu*X-VS:1234567890"
+        );
+        let escaped = BankPayment {
+            message: Some("100% * 2"),
+            ..payment
+        };
+        assert!(spayd_string(&escaped)
+            .unwrap()
+            .contains("*MSG:100%25 %2A 2*"));
+
+        for date in [
+            "20240230",
+            "2023-02-29",
+            "20240431",
+            "20241301",
+            "20240100",
+            "2024-1-1",
+        ]
+        .iter()
+        {
+            assert!(parse_date(date).is_err(), "{}", date);
+        }
+        assert_eq!(parse_date("2000-02-29").unwrap(), "20000229");
+        assert!(parse_date("19000229").is_err());
+    }
+
+    #[test]
+    fn paybysquare_round_trip() {
+        use lzma_rust2::LzmaReader;
+        use std::io::Read;
+
+        let payment = BankPayment {
+            iban: "SK3112000000198742637541",
+            bic: Some("TATRSKBX"),
+            amount: Some("25.3"),
+            currency: "EUR",
+            due_date: Some("2024-01-31"),
+            variable_symbol: Some("123"),
+            name: Some("Jan Novak"),
+            message: Some("invoice 7"),
+            ..BankPayment::default()
+        };
+        let code = paybysquare_string(&payment).unwrap();
+
+        // back from base32hex, the standard base32 decoder only needs the other alphabet
+        let hex = "0123456789ABCDEFGHIJKLMNOPQRSTUV";
+        let standard = "ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+        let translated = code
+            .chars()
+            .map(|c| standard.as_bytes()[hex.find(c).unwrap()] as char)
+            .collect::<String>();
+        let bytes = base32_decode(&translated).unwrap();
+
+        // a payment order header, the length of the checksummed data, then raw LZMA
+        assert_eq!(bytes[..2], [0, 0]);
+        let length = u16::from_le_bytes([bytes[2], bytes[3]]) as usize;
+        let mut total = Vec::new();
+        LzmaReader::new(&bytes[4..], length as u64, 3, 0, 2, 128 * 1024, None)
+            .unwrap()
+            .read_to_end(&mut total)
+            .unwrap();
+        assert_eq!(total.len(), length);
+
+        let (crc, data) = total.split_at(4);
+        assert_eq!(crc, crc32fast::hash(data).to_le_bytes());
+        assert_eq!(
+            std::str::from_utf8(data).unwrap(),
+            "\t1\t1\t25.30\tEUR\t20240131\t123\t\t\t\tinvoice 7\t1\t\
+             SK3112000000198742637541\tTATRSKBX\t0\t0\tJan Novak\t\t"
+        );
+    }
+
+    const TEST_PGP_KEY: &str = "-----BEGIN PGP PUBLIC KEY BLOCK-----
+
+mDMEatXjbxYJKwYBBAHaRw8BAQdAv3SSR727b+GlbZ8Vs25ObWbZx4eKdlADk0vO
+zuLKQDC0HnFydGVybSB0ZXN0IDx0ZXN0QGV4YW1wbGUub3JnPoiQBBMWCAA4FiEE
+YUrK2ldhBZtk8ocm9uBTph7LptMFAmrV428CGwMFCwkIBwIGFQoJCAsCBBYCAwEC
+HgECF4AACgkQ9uBTph7LptOWNAD+J+JOzt+1/9FvD8DTBLIyvmlbohZhZpmYdNu9
+SeyFWD0A/RHHqn63+zhhHBi3T9LPR4O6w9fvhuCFZnI8KJxjIG8B
+=Ywfv
+-----END PGP PUBLIC KEY BLOCK-----
+";
+
+    #[test]
+    fn openpgp_fingerprints() {
+        // as listed by gpg --with-colons
+        let fingerprint = "614ACADA5761059B64F28726F6E053A61ECBA6D3";
+        assert_eq!(openpgp_fingerprint(TEST_PGP_KEY).unwrap(), fingerprint);
+        assert_eq!(
+            openpgp_string(&openpgp_fingerprint(TEST_PGP_KEY).unwrap()).unwrap(),
+            format!("OPENPGP4FPR:{}", fingerprint)
+        );
+
+        // the same key packet behind a two byte old format and a new format header
+        let body = TEST_PGP_KEY.lines().skip(2).take(5).collect::<String>();
+        let data = base64_decode(&body).unwrap();
+        assert_eq!(data[..2], [0x98, 0x33]);
+        let armor = |data: &[u8]| {
+            format!(
+                "-----BEGIN PGP PUBLIC KEY BLOCK-----\n\n{}\n-----END PGP PUBLIC KEY BLOCK-----\n",
+                base64_encode(data, false)
+            )
+        };
+        let old_format = [&[0x99, 0x00][..], &data[1..]].concat();
+        let new_format = [&[0xc6][..], &data[1..]].concat();
+        assert_eq!(
+            openpgp_fingerprint(&armor(&old_format)).unwrap(),
+            fingerprint
+        );
+        assert_eq!(
+            openpgp_fingerprint(&armor(&new_format)).unwrap(),
+            fingerprint
+        );
+
+        // a v5 key, a user id packet first and a truncated key
+        let v5 = [&data[..2], &[5], &data[3..]].concat();
+        assert!(openpgp_fingerprint(&armor(&v5)).is_err());
+        assert!(openpgp_fingerprint(&armor(&data[53..])).is_err());
+        assert!(openpgp_fingerprint(&armor(&data[..20])).is_err());
+        assert!(openpgp_fingerprint("mDMEatXjbxYJKwYBBAHaRw8BAQdA").is_err());
+    }
+
+    #[test]
+    fn ssh_fingerprints() {
+        let key = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIHdXzVE1VRkWJwkpizjUo0HTn2t8h1c5yu60vEsjvOb/ test@example.org";
+        // as printed by ssh-keygen -lf
+        assert_eq!(
+            ssh_string(key, false).unwrap(),
+            "SHA256:4600bH292aIyQRy1SG/VGEdu2JYGLK12HQmRBDL6iAg"
+        );
+        assert_eq!(
+            ssh_string(&format!("# comment\n\n{}\n", key), true).unwrap(),
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIHdXzVE1VRkWJwkpizjUo0HTn2t8h1c5yu60vEsjvOb/"
+        );
+        assert!(ssh_string(&key.replace("ssh-ed25519", "ssh-rsa"), false).is_err());
+        assert!(ssh_string("ssh-ed25519 AAAAC3NzaC1lZDI1", false).is_err());
+        assert!(ssh_string("ssh-ed25519", false).is_err());
+    }
+
+    #[test]
+    fn magnet_links() {
+        let torrent =
+            |info: &str, trackers: &str| format!("d{}4:info{}e", trackers, info).into_bytes();
+        // the keys are deliberately unsorted, the hash has to cover the bytes as they are
+        let single =
+            "d4:name8:file.txt6:lengthi1234e12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaae";
+        let (link, dropped) = magnet_string(&torrent(single, ""), 40, EcLevel::L).unwrap();
+        assert_eq!(
+            link,
+            "magnet:?xt=urn:btih:9a7cbc1e8dc4d05721d112b555919ff4ac7c447e&dn=file.txt&xl=1234"
+        );
+        assert_eq!(dropped, 0);
+
+        let multi = "d5:filesld6:lengthi100e4:pathl1:aeed6:lengthi23e4:pathl1:b1:ceee\
+                     4:name3:dir12:piece lengthi16384e6:pieces20:bbbbbbbbbbbbbbbbbbbbe";
+        let (link, _) = magnet_string(&torrent(multi, ""), 40, EcLevel::L).unwrap();
+        assert!(link.starts_with("magnet:?xt=urn:btih:78f1a87f2dcb9832c5317375be720b3ae9b4085a&"));
+        assert!(link.ends_with("&xl=123"));
+
+        let tree = "d9:file treed5:a.txtd0:d6:lengthi100e11:pieces root32:rrrrrrrrrrrrrrrrrrrrrrrrrrrrrrrree\
+                    3:subd5:b.txtd0:d6:lengthi23eeeee12:meta versioni2e4:name4:tree12:piece lengthi16384ee";
+        let (link, _) = magnet_string(&torrent(tree, ""), 40, EcLevel::L).unwrap();
+        assert_eq!(
+            link,
+            "magnet:?xt=urn:btmh:1220f8f844e66460786ad8f1baf44cee5f8c82155c8e5e1ce7e9d8a0153bbe08f3a7\
+             &dn=tree&xl=123"
+        );
+        // hybrid torrents carry both hashes
+        let hybrid = tree.replacen("4:name", "6:pieces20:cccccccccccccccccccc4:name", 1);
+        let (link, _) = magnet_string(&torrent(&hybrid, ""), 40, EcLevel::L).unwrap();
+        assert!(link.contains("xt=urn:btih:") && link.contains("&xt=urn:btmh:1220"));
+    }
+
+    #[test]
+    fn magnet_trackers() {
+        let single =
+            "d4:name8:file.txt6:lengthi1234e12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaae";
+        let tracker = |i| format!("http://tracker{}.example.org:6969/announce", i);
+        let tiers = (0..20)
+            .map(|i| format!("l{}:{}e", tracker(i).len(), tracker(i)))
+            .collect::<String>();
+        let torrent = format!(
+            "d8:announce{}:{}13:announce-listl{}e4:info{}e",
+            tracker(0).len(),
+            tracker(0),
+            tiers,
+            single
+        )
+        .into_bytes();
+
+        // the announce url repeats the first tier and is only listed once
+        let (link, dropped) = magnet_string(&torrent, 40, EcLevel::L).unwrap();
+        assert_eq!(dropped, 0);
+        assert_eq!(link.matches("&tr=").count(), 20);
+
+        let (small, dropped) = magnet_string(&torrent, 10, EcLevel::L).unwrap();
+        assert!(dropped > 0);
+        assert_eq!(small.matches("&tr=").count(), 20 - dropped);
+        assert!(QrCode::with_version(&small, Version::Normal(10), EcLevel::L).is_ok());
+        assert!(magnet_string(&torrent, 2, EcLevel::L).is_err());
+    }
+
+    #[test]
+    fn gs1_check_digits() {
+        assert_eq!(gs1_check_digit("0950600013435"), 2);
+        assert_eq!(gs1_check_digit("400638133393"), 1);
+        assert_eq!(gs1_check_digit("10614141123456789"), 7);
+        // shorter GTINs are padded to 14 digits
+        assert_eq!(gs1_check("01", "9506000134352").unwrap(), "09506000134352");
+        assert_eq!(gs1_check("01", "4006381333931").unwrap(), "04006381333931");
+        assert!(gs1_check("01", "09506000134353").is_err());
+        assert!(gs1_check("00", "106141411234567890").is_err());
+    }
+
+    #[test]
+    fn gs1_value_checks() {
+        assert_eq!(gs1_check("10", "ABC-1/2").unwrap(), "ABC-1/2");
+        assert_eq!(gs1_check("17", "251200").unwrap(), "251200");
+        assert_eq!(gs1_check("3103", "000750").unwrap(), "000750");
+        let errors = [
+            ("10", "A".repeat(21), "the AI (10) needs 1 to 20 characters"),
+            (
+                "10",
+                "AB C".to_string(),
+                "the AI (10) needs 1 to 20 characters",
+            ),
+            ("20", "A1".to_string(), "the AI (20) needs exactly 2 digits"),
+            (
+                "01",
+                "123456789012345".to_string(),
+                "the AI (01) needs exactly 14 digits",
+            ),
+            (
+                "17",
+                "251300".to_string(),
+                "the AI (17) needs a date like YYMMDD",
+            ),
+            (
+                "17",
+                "251232".to_string(),
+                "the AI (17) needs a date like YYMMDD",
+            ),
+            (
+                "7003",
+                "2501011200".to_string(),
+                "the AI (7003) is not supported",
+            ),
+        ];
+        for (ai, value, error) in errors.iter() {
+            assert_eq!(gs1_check(ai, value).unwrap_err(), *error);
+        }
+    }
+
+    #[test]
+    fn gs1_element_strings() {
+        let ais = [
+            ("10", "ABC1"),
+            ("21", "XYZ"),
+            ("01", "9506000134352"),
+            ("17", "201231"),
+        ];
+        // fixed length AIs first, GS only after a variable length value that is followed by more
+        assert_eq!(
+            gs1_string(&ais, false, "").unwrap(),
+            "010950600013435217201231\
+             10ABC1\u{1d}21XYZ"
+        );
+        assert_eq!(gs1_string(&ais[..1], false, "").unwrap(), "10ABC1");
+        assert!(gs1_string(&[], false, "").is_err());
+        assert!(gs1_string(&[("10", "A"), ("10", "B")], false, "").is_err());
+    }
+
+    #[test]
+    fn gs1_digital_links() {
+        let ais = [
+            ("17", "201231"),
+            ("21", "12345"),
+            ("10", "ABC1"),
+            ("01", "9506000134352"),
+            ("22", "2A"),
+            ("3103", "000750"),
+        ];
+        // the GTIN and its qualifiers in the order 22, 10, 21 form the path, the rest the query
+        assert_eq!(
+            gs1_string(&ais, true, "https://id.gs1.org/").unwrap(),
+            "https://id.gs1.org/01/09506000134352/22/2A/10/ABC1/21/12345?17=201231&3103=000750"
+        );
+        assert_eq!(
+            gs1_string(
+                &[("414", "9520123456788"), ("254", "32a")],
+                true,
+                "https://example.com"
+            )
+            .unwrap(),
+            "https://example.com/414/9520123456788/254/32a"
+        );
+        assert!(gs1_string(&[("10", "ABC1")], true, "https://id.gs1.org").is_err());
+    }
 }
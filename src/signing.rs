@@ -0,0 +1,199 @@
+use crate::encoding::{base64_decode, base64_encode};
+use ed25519_dalek::pkcs8::spki::der::pem::LineEnding;
+use ed25519_dalek::pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePrivateKey, EncodePublicKey};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use std::convert::TryFrom;
+
+// the signature is appended as ";SIG=<base64url>" and the expiry as ";EXP=<unix time>" before it,
+// so the signed data is everything in front of ";SIG=". EXP is always written, empty without an
+// expiry, so a payload that itself ends in ";EXP=<digits>" is not taken for a signed expiry
+const SIGNATURE_MARKER: &str = ";SIG=";
+const EXPIRY_MARKER: &str = ";EXP=";
+
+// signs the payload with a PKCS#8 Ed25519 key, optionally binding an expiry time to it
+#[allow(dead_code)]
+pub fn sign_payload(payload: &str, key_pem: &str, expires: Option<u64>) -> Result<String, String> {
+    let key = parse_private_key(key_pem)?;
+
+    let expires = expires.map(|e| e.to_string()).unwrap_or_default();
+    let signed = format!("{}{}{}", payload, EXPIRY_MARKER, expires);
+    let signature = key.sign(signed.as_bytes());
+    Ok(format!(
+        "{}{}{}",
+        signed,
+        SIGNATURE_MARKER,
+        base64_encode(&signature.to_bytes(), true)
+    ))
+}
+
+// checks a signed payload and returns the original payload and its expiry time
+#[allow(dead_code)]
+pub fn verify_payload(
+    signed: &str,
+    public_key: &str,
+    now: u64,
+) -> Result<(String, Option<u64>), String> {
+    let key = parse_public_key(public_key)?;
+    let (data, signature) = signed
+        .rsplit_once(SIGNATURE_MARKER)
+        .ok_or_else(|| "the payload is not signed".to_string())?;
+    let signature = base64_decode(signature.trim_end())
+        .and_then(|s| Signature::from_slice(&s).ok())
+        .ok_or_else(|| "the signature is malformed".to_string())?;
+    key.verify(data.as_bytes(), &signature).map_err(|_| {
+        "the signature does not match, the payload was forged or altered".to_string()
+    })?;
+
+    let (payload, expires) = match data.rsplit_once(EXPIRY_MARKER) {
+        Some((p, "")) => (p, None),
+        Some((p, e)) => {
+            let e = e
+                .parse::<u64>()
+                .map_err(|_| "the expiry is malformed".to_string())?;
+            (p, Some(e))
+        }
+        None => return Err("the signed payload has no expiry field".to_string()),
+    };
+    if let Some(e) = expires {
+        if e < now {
+            return Err(format!("the signature expired at {}", format_time(e)));
+        }
+    }
+    Ok((payload.to_string(), expires))
+}
+
+// creates a key pair, returning the private key as PKCS#8 PEM and the public key as SPKI PEM
+#[allow(dead_code)]
+pub fn generate_signing_key() -> Result<(String, String), String> {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).map_err(|e| e.to_string())?;
+    let key = SigningKey::from_bytes(&bytes);
+    let private = key
+        .to_pkcs8_pem(LineEnding::LF)
+        .map_err(|e| e.to_string())?;
+    let public = key
+        .verifying_key()
+        .to_public_key_pem(LineEnding::LF)
+        .map_err(|e| e.to_string())?;
+    Ok((private.to_string(), public))
+}
+
+// checks that a key file holds a PKCS#8 Ed25519 private key as written by keygen
+#[allow(dead_code)]
+pub fn parse_private_key(key_pem: &str) -> Result<SigningKey, String> {
+    SigningKey::from_pkcs8_pem(key_pem)
+        .map_err(|_| "the key file is not a PKCS#8 Ed25519 private key".to_string())
+}
+
+// accepts a SPKI PEM as written by keygen or the raw 32 byte key in base64
+fn parse_public_key(key: &str) -> Result<VerifyingKey, String> {
+    if key.contains("-----BEGIN PUBLIC KEY-----") {
+        return VerifyingKey::from_public_key_pem(key)
+            .map_err(|_| "the public key is not an Ed25519 key".to_string());
+    }
+    base64_decode(key.trim())
+        .and_then(|k| <[u8; 32]>::try_from(k.as_slice()).ok())
+        .and_then(|k| VerifyingKey::from_bytes(&k).ok())
+        .ok_or_else(|| "the public key is neither a PEM file nor 32 base64 bytes".to_string())
+}
+
+// takes a unix time or a duration from now like 90m, 12h, 30d or 2w
+#[allow(dead_code)]
+pub fn parse_expiry(inp: &str, now: u64) -> Result<u64, String> {
+    let err = || format!("{:?} is neither a unix time nor a duration like 30d", inp);
+    let unit = match inp.chars().last() {
+        Some('s') => 1,
+        Some('m') => 60,
+        Some('h') => 60 * 60,
+        Some('d') => 24 * 60 * 60,
+        Some('w') => 7 * 24 * 60 * 60,
+        _ => return inp.parse::<u64>().map_err(|_| err()),
+    };
+    let amount = inp[..inp.len() - 1].parse::<u64>().map_err(|_| err())?;
+    amount
+        .checked_mul(unit)
+        .and_then(|d| d.checked_add(now))
+        .ok_or_else(err)
+}
+
+// formats a unix time as an UTC date, e.g. 2024-01-31 12:00:00 UTC
+#[allow(dead_code)]
+pub fn format_time(time: u64) -> String {
+    // the days to civil date conversion from Howard Hinnant's date algorithms
+    let days = (time / 86400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    let seconds = time % 86400;
+    format!(
+        "{}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signed_payloads_verify() {
+        let (private, public) = generate_signing_key().unwrap();
+        let signed = sign_payload("WIFI:S:home;;", &private, None).unwrap();
+        assert_eq!(
+            verify_payload(&signed, &public, 0).unwrap(),
+            ("WIFI:S:home;;".to_string(), None)
+        );
+
+        let expiring = sign_payload("hello", &private, Some(1000)).unwrap();
+        assert_eq!(
+            verify_payload(&expiring, &public, 999).unwrap(),
+            ("hello".to_string(), Some(1000))
+        );
+        assert!(verify_payload(&expiring, &public, 1001).is_err());
+        // moving the expiry breaks the signature
+        let extended = expiring.replace(";EXP=1000", ";EXP=9999");
+        assert!(verify_payload(&extended, &public, 1001).is_err());
+    }
+
+    #[test]
+    fn only_signed_expiries_are_enforced() {
+        let (private, public) = generate_signing_key().unwrap();
+        // the payload's own text looks like an expiry, but none was signed
+        let signed = sign_payload("order;EXP=123", &private, None).unwrap();
+        assert!(signed.starts_with("order;EXP=123;EXP=;SIG="));
+        assert_eq!(
+            verify_payload(&signed, &public, 1000).unwrap(),
+            ("order;EXP=123".to_string(), None)
+        );
+
+        let expiring = sign_payload("order;EXP=123", &private, Some(2000)).unwrap();
+        assert_eq!(
+            verify_payload(&expiring, &public, 1000).unwrap(),
+            ("order;EXP=123".to_string(), Some(2000))
+        );
+        assert!(verify_payload(&expiring, &public, 2001).is_err());
+    }
+
+    #[test]
+    fn forged_payloads_are_rejected() {
+        let (private, public) = generate_signing_key().unwrap();
+        let (_, other_public) = generate_signing_key().unwrap();
+        let signed = sign_payload("pay 10 EUR", &private, None).unwrap();
+
+        assert!(verify_payload(&signed.replace("10", "99"), &public, 0).is_err());
+        assert!(verify_payload(&signed, &other_public, 0).is_err());
+        assert!(verify_payload("pay 10 EUR", &public, 0).is_err());
+        assert!(sign_payload("hello", &public, None).is_err());
+    }
+}
@@ -0,0 +1,326 @@
+use crate::payloads::Authentication;
+use std::collections::HashMap;
+use std::fs;
+
+/// The parts of a saved network that end up in a Wi-Fi code.
+#[derive(Debug)]
+pub struct WifiConfig {
+    pub ssid: String,
+    pub password: String,
+    pub mode: Authentication,
+    pub hidden: bool,
+    pub eap_method: Option<String>,
+    pub identity: Option<String>,
+    pub anonymous_identity: Option<String>,
+    pub phase2: Option<String>,
+}
+
+// reads a NetworkManager keyfile or a wpa_supplicant.conf, optionally picking a network by ssid
+pub fn read_wifi_config(path: &str, ssid: Option<&str>) -> Result<WifiConfig, String> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("could not read {:?}: {}", path, e))?;
+
+    let mut configs = if content.contains("network={") {
+        parse_wpa_supplicant(&content)?
+    } else {
+        vec![parse_nmconnection(&content)?]
+    };
+
+    match ssid {
+        Some(s) => match configs.iter().position(|c| c.ssid == s) {
+            Some(i) => Ok(configs.swap_remove(i)),
+            None => Err(format!("there is no network {:?} in {:?}", s, path)),
+        },
+        None if configs.len() == 1 => Ok(configs.remove(0)),
+        None => Err(format!(
+            "{:?} contains several networks, pick one by its ssid: {}",
+            path,
+            configs
+                .iter()
+                .map(|c| format!("{:?}", c.ssid))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+// NetworkManager keyfiles are ini files with [wifi], [wifi-security] and [802-1x] sections
+fn parse_nmconnection(content: &str) -> Result<WifiConfig, String> {
+    let mut sections: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut current = String::new();
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            current = line[1..line.len() - 1].to_string();
+        } else if let Some(i) = line.find('=') {
+            sections.entry(current.clone()).or_default().insert(
+                line[..i].trim().to_string(),
+                unescape_keyfile(&line[i + 1..]),
+            );
+        }
+    }
+
+    let empty = HashMap::new();
+    let wifi = sections
+        .get("wifi")
+        .ok_or_else(|| "the keyfile has no [wifi] section".to_string())?;
+    let security = sections.get("wifi-security").unwrap_or(&empty);
+    let dot1x = sections.get("802-1x").unwrap_or(&empty);
+    let get = |section: &HashMap<String, String>, key: &str| section.get(key).cloned();
+
+    let ssid = get(wifi, "ssid").ok_or_else(|| "the keyfile has no ssid".to_string())?;
+    let key_mgmt = get(security, "key-mgmt").unwrap_or_else(|| "open".to_string());
+    let (mode, password) = match key_mgmt.as_str() {
+        "wpa-psk" => (Authentication::WPA, get(security, "psk")),
+        "sae" => (Authentication::SAE, get(security, "psk")),
+        "wpa-eap" | "wpa-eap-suite-b-192" => (Authentication::WPA2_EAP, get(dot1x, "password")),
+        "none" => {
+            let index = get(security, "wep-tx-keyidx").unwrap_or_else(|| "0".to_string());
+            (
+                Authentication::WEP,
+                get(security, &format!("wep-key{}", index)),
+            )
+        }
+        "open" | "owe" => (Authentication::nopass, Some("".to_string())),
+        other => return Err(format!("the key management {:?} is not supported", other)),
+    };
+    let password = password.ok_or_else(|| {
+        "the password is not stored in the file (it may be kept in a keyring)".to_string()
+    })?;
+
+    let is_eap = mode == Authentication::WPA2_EAP;
+    Ok(WifiConfig {
+        ssid,
+        password,
+        mode,
+        hidden: get(wifi, "hidden").is_some_and(|h| h == "true"),
+        eap_method: get(dot1x, "eap")
+            .filter(|_| is_eap)
+            .map(|e| e.split(';').next().unwrap_or("").to_string()),
+        identity: get(dot1x, "identity").filter(|_| is_eap),
+        anonymous_identity: get(dot1x, "anonymous-identity").filter(|_| is_eap),
+        phase2: get(dot1x, "phase2-auth").filter(|_| is_eap),
+    })
+}
+
+// keyfiles escape like glib key files: \s, \n, \t, \\ and friends
+fn unescape_keyfile(value: &str) -> String {
+    let mut out = String::new();
+    let mut chars = value.trim_start().chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => out.push(' '),
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+// wpa_supplicant.conf keeps one network={ ... } block per network
+fn parse_wpa_supplicant(content: &str) -> Result<Vec<WifiConfig>, String> {
+    let mut configs = Vec::new();
+    let mut block: Option<HashMap<String, String>> = None;
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with("network={") {
+            block = Some(HashMap::new());
+        } else if line == "}" {
+            if let Some(b) = block.take() {
+                configs.push(wpa_supplicant_network(&b)?);
+            }
+        } else if let (Some(b), Some(i)) = (block.as_mut(), line.find('=')) {
+            b.insert(
+                line[..i].trim().to_string(),
+                line[i + 1..].trim().to_string(),
+            );
+        }
+    }
+
+    if configs.is_empty() {
+        return Err("the wpa_supplicant file has no network blocks".to_string());
+    }
+    Ok(configs)
+}
+
+fn wpa_supplicant_network(block: &HashMap<String, String>) -> Result<WifiConfig, String> {
+    let get = |key: &str| block.get(key).map(|v| wpa_supplicant_value(key, v));
+
+    let ssid = match get("ssid") {
+        Some(Ok(s)) => s,
+        Some(Err(e)) => return Err(e),
+        None => return Err("a network block has no ssid".to_string()),
+    };
+    let key_mgmt = block
+        .get("key_mgmt")
+        .map(String::as_str)
+        .unwrap_or("WPA-PSK WPA-EAP");
+    let methods = key_mgmt.split_whitespace().collect::<Vec<_>>();
+    let wep_key = format!(
+        "wep_key{}",
+        block
+            .get("wep_tx_keyidx")
+            .map(String::as_str)
+            .unwrap_or("0")
+    );
+
+    // WPA covers the WPA2/WPA3 transition mode, so SAE is only used when it stands alone
+    let mode = if methods.contains(&"WPA-PSK") && block.contains_key("psk") {
+        Authentication::WPA
+    } else if methods.contains(&"SAE") {
+        Authentication::SAE
+    } else if methods.iter().any(|m| m.starts_with("WPA-EAP")) && block.contains_key("eap") {
+        Authentication::WPA2_EAP
+    } else if methods.contains(&"NONE") && block.contains_key(&wep_key) {
+        Authentication::WEP
+    } else if methods.contains(&"NONE") || methods.contains(&"OWE") {
+        Authentication::nopass
+    } else {
+        return Err(format!(
+            "the key management {:?} is not supported",
+            key_mgmt
+        ));
+    };
+
+    let password_key = match mode {
+        Authentication::WPA => "psk",
+        Authentication::SAE => {
+            if block.contains_key("sae_password") {
+                "sae_password"
+            } else {
+                "psk"
+            }
+        }
+        Authentication::WPA2_EAP => "password",
+        Authentication::WEP => &wep_key,
+        Authentication::nopass => "",
+    };
+    let password = match get(password_key) {
+        Some(p) => p?,
+        None if password_key.is_empty() => "".to_string(),
+        None => {
+            return Err(format!(
+                "the network {:?} has no {} stored in the file",
+                ssid, password_key
+            ))
+        }
+    };
+
+    let is_eap = mode == Authentication::WPA2_EAP;
+    let eap_field = |key: &str| match get(key) {
+        Some(Ok(v)) if is_eap => Some(v),
+        _ => None,
+    };
+    Ok(WifiConfig {
+        ssid,
+        password,
+        mode,
+        hidden: block.get("scan_ssid").is_some_and(|s| s == "1"),
+        eap_method: eap_field("eap").map(|e| e.split_whitespace().next().unwrap_or("").to_string()),
+        identity: eap_field("identity"),
+        anonymous_identity: eap_field("anonymous_identity"),
+        // phase2 reads like "auth=MSCHAPV2" or "autheap=MSCHAPV2"
+        phase2: eap_field("phase2").map(|p| match p.find('=') {
+            Some(i) => p[i + 1..].to_string(),
+            None => p,
+        }),
+    })
+}
+
+// values are either "quoted strings" or unquoted hex, as used for raw ssids and psks
+fn wpa_supplicant_value(key: &str, value: &str) -> Result<String, String> {
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        return Ok(value[1..value.len() - 1].to_string());
+    }
+    // a 64 digit psk and hex WEP keys are raw keys and stay hex, as do unknown unquoted values
+    let is_raw_key = (key == "psk" && value.len() == 64) || key.starts_with("wep_key");
+    if is_raw_key || !value.is_ascii() || value.len() % 2 != 0 {
+        return Ok(value.to_string());
+    }
+    let bytes = (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&value[i..i + 2], 16))
+        .collect::<Result<Vec<u8>, _>>();
+    match bytes {
+        Ok(b) => String::from_utf8(b).map_err(|_| format!("{:?} is not valid utf-8", value)),
+        Err(_) => Ok(value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nmconnection_keyfiles() {
+        let config = parse_nmconnection(
+            "[connection]\nid=Home\n\n[wifi]\nssid=Home Net\nhidden=true\n\n\
+             [wifi-security]\nkey-mgmt=wpa-psk\npsk=secret\\s123\n",
+        )
+        .unwrap();
+        assert_eq!(config.ssid, "Home Net");
+        assert_eq!(config.password, "secret 123");
+        assert_eq!(config.mode, Authentication::WPA);
+        assert!(config.hidden);
+
+        let config = parse_nmconnection(
+            "[wifi]\nssid=Old\n[wifi-security]\nkey-mgmt=none\nwep-tx-keyidx=2\n\
+             wep-key0=aaaaa\nwep-key2=ccccc\n",
+        )
+        .unwrap();
+        assert_eq!(config.mode, Authentication::WEP);
+        assert_eq!(config.password, "ccccc");
+
+        assert!(
+            parse_nmconnection("[wifi]\nssid=Home\n[wifi-security]\nkey-mgmt=wpa-psk\n").is_err()
+        );
+    }
+
+    #[test]
+    fn wpa_supplicant_values() {
+        let raw_psk = "0123456789abcdef".repeat(4);
+        let hex_ssid = "4c6f6e67204e616d6520666f722061206e6574776f726b2c2033322062797465";
+        let content = format!(
+            "ctrl_interface=/run/wpa_supplicant\n\
+             network={{\n  ssid=\"Home\"\n  psk=\"secret123\"\n}}\n\
+             network={{\n  ssid=4f6666696365\n  psk={}\n  scan_ssid=1\n}}\n\
+             network={{\n  ssid={}\n  psk=\"pw123456\"\n}}\n\
+             network={{\n  ssid=Ünïcodé\n  key_mgmt=NONE\n}}\n",
+            raw_psk, hex_ssid
+        );
+        let configs = parse_wpa_supplicant(&content).unwrap();
+        assert_eq!(configs.len(), 4);
+
+        assert_eq!(configs[0].ssid, "Home");
+        assert_eq!(configs[0].password, "secret123");
+        assert_eq!(configs[1].ssid, "Office");
+        assert_eq!(configs[1].password, raw_psk);
+        assert!(configs[1].hidden);
+        // a 64 digit ssid is still hex, only psks are raw at that length
+        assert_eq!(configs[2].ssid, "Long Name for a network, 32 byte");
+        assert_eq!(configs[3].ssid, "Ünïcodé");
+        assert_eq!(configs[3].mode, Authentication::nopass);
+    }
+
+    #[test]
+    fn wpa_supplicant_wep_key_index() {
+        let configs = parse_wpa_supplicant(
+            "network={\n  ssid=\"Old\"\n  key_mgmt=NONE\n  wep_key0=\"first\"\n\
+             wep_key1=0102030405\n  wep_tx_keyidx=1\n}\n",
+        )
+        .unwrap();
+        assert_eq!(configs[0].mode, Authentication::WEP);
+        assert_eq!(configs[0].password, "0102030405");
+    }
+}